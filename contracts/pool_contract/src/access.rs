@@ -0,0 +1,75 @@
+// Role-based access control for PoolContract
+//
+// The admin only manages roles; every privileged entry point instead takes
+// the calling address and checks that it holds the role for that operation.
+// This lets the backend's hot key settle orders without being able to
+// upgrade the contract or withdraw fees.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::{events, DataKey, PoolError};
+
+/// Roles that can be granted to addresses by the admin
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    ConfigAdmin, // fee terms, seller, tokens, Blend pools and escrow period
+    Settler,     // release, refund, settle and Blend supply (the backend job)
+    Treasurer,   // fee withdrawals
+    Pauser,      // emergency pause
+    Upgrader,    // WASM upgrades
+}
+
+pub const ALL_ROLES: [Role; 5] = [
+    Role::ConfigAdmin,
+    Role::Settler,
+    Role::Treasurer,
+    Role::Pauser,
+    Role::Upgrader,
+];
+
+pub fn has_role(env: &Env, role: Role, account: &Address) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::RoleMember(role, account.clone()))
+}
+
+pub fn grant(env: &Env, role: Role, account: &Address) {
+    let key = DataKey::RoleMember(role, account.clone());
+    if env.storage().instance().has(&key) {
+        return;
+    }
+    env.storage().instance().set(&key, &true);
+    events::RoleGranted { role, account: account.clone() }.publish(env);
+}
+
+pub fn revoke(env: &Env, role: Role, account: &Address) {
+    let key = DataKey::RoleMember(role, account.clone());
+    if !env.storage().instance().has(&key) {
+        return;
+    }
+    env.storage().instance().remove(&key);
+    events::RoleRevoked { role, account: account.clone() }.publish(env);
+}
+
+/// Move every role `from` holds to `to` (used when the admin changes hands)
+pub fn transfer_roles(env: &Env, from: &Address, to: &Address) {
+    for role in ALL_ROLES {
+        if has_role(env, role, from) {
+            revoke(env, role, from);
+            grant(env, role, to);
+        }
+    }
+}
+
+/// Require `caller`'s authorization and membership of `role`
+pub fn require_role(env: &Env, caller: &Address, role: Role) -> Result<(), PoolError> {
+    if !env.storage().instance().has(&DataKey::Admin) {
+        return Err(PoolError::NotInitialized);
+    }
+    caller.require_auth();
+    if !has_role(env, role, caller) {
+        return Err(PoolError::Unauthorized);
+    }
+    Ok(())
+}
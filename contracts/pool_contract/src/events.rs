@@ -0,0 +1,275 @@
+// Contract events for PoolContract state changes
+//
+// Every state-changing entry point publishes one of these so an indexer can
+// follow the pool without parsing debug logs. The first topic of each event is
+// fixed below and must not change once deployed; order ids and addresses that
+// indexers filter on are additional topics.
+
+use soroban_sdk::{contractevent, Address, BytesN, Map, Vec};
+
+use crate::{ChangeKind, ConfigChange, FeeTerms, ReserveConfig, Role};
+
+#[contractevent(topics = ["initialized"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Initialized {
+    pub admin: Address,
+    pub seller: Address,
+    pub fee_terms: FeeTerms,
+    pub tokens: Vec<Address>,
+}
+
+#[contractevent(topics = ["deposit"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Deposited {
+    #[topic]
+    pub order_id: u64,
+    #[topic]
+    pub buyer: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub expires_at: u64,
+}
+
+#[contractevent(topics = ["release"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Released {
+    #[topic]
+    pub order_id: u64,
+    pub seller: Address,
+    pub token: Address,
+    pub seller_share: i128,
+    pub fee: i128,
+}
+
+#[contractevent(topics = ["refund"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Refunded {
+    #[topic]
+    pub order_id: u64,
+    pub buyer: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["reclaim"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reclaimed {
+    #[topic]
+    pub order_id: u64,
+    pub buyer: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["settle"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settled {
+    pub seller: Address,
+    pub paid: Map<Address, i128>, // seller payout per token
+    pub fees: Map<Address, i128>, // fees retained per token
+    pub orders_released: u32,
+    pub orders_deferred: u32,
+}
+
+#[contractevent(topics = ["settle_deferred"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SettlementDeferred {
+    #[topic]
+    pub order_id: u64,
+    pub token: Address,
+    pub seller_share: i128,
+}
+
+#[contractevent(topics = ["blend_supply"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SuppliedToBlend {
+    #[topic]
+    pub token: Address,
+    pub pool: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["blend_supply_failed"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SupplyFailed {
+    #[topic]
+    pub token: Address,
+    pub pool: Address,
+    pub amount: i128,
+    pub error: u32, // `PoolError` code
+}
+
+#[contractevent(topics = ["blend_withdraw"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WithdrawnFromBlend {
+    #[topic]
+    pub token: Address,
+    pub pool: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["blend_withdraw_failed"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WithdrawFailed {
+    #[topic]
+    pub token: Address,
+    pub pool: Address,
+    pub error: u32, // `PoolError` code
+}
+
+#[contractevent(topics = ["fees_withdrawn"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeesWithdrawn {
+    #[topic]
+    pub token: Address,
+    #[topic]
+    pub recipient: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["yield_withdrawn"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct YieldWithdrawn {
+    #[topic]
+    pub token: Address,
+    #[topic]
+    pub recipient: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["emissions_claimed"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmissionsClaimed {
+    pub pool: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["config", "seller"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SellerUpdated {
+    pub seller: Address,
+}
+
+#[contractevent(topics = ["config", "fee_terms"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeTermsUpdated {
+    pub fee_terms: FeeTerms,
+}
+
+#[contractevent(topics = ["config", "max_fee"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaxFeeUpdated {
+    pub max_fee_bps: u32,
+}
+
+#[contractevent(topics = ["change_queued"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChangeQueued {
+    #[topic]
+    pub kind: ChangeKind,
+    pub change: ConfigChange,
+    pub effective_at: u64,
+}
+
+#[contractevent(topics = ["change_cancelled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChangeCancelled {
+    #[topic]
+    pub kind: ChangeKind,
+}
+
+#[contractevent(topics = ["config", "escrow_period"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowPeriodUpdated {
+    pub escrow_period: u64,
+}
+
+#[contractevent(topics = ["config", "reserve"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReserveUpdated {
+    #[topic]
+    pub token: Address,
+    pub config: ReserveConfig,
+}
+
+#[contractevent(topics = ["config", "blnd_token"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlndTokenUpdated {
+    pub token: Address,
+}
+
+#[contractevent(topics = ["role_granted"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoleGranted {
+    #[topic]
+    pub role: Role,
+    #[topic]
+    pub account: Address,
+}
+
+#[contractevent(topics = ["role_revoked"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoleRevoked {
+    #[topic]
+    pub role: Role,
+    #[topic]
+    pub account: Address,
+}
+
+#[contractevent(topics = ["admin_proposed"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminProposed {
+    #[topic]
+    pub new_admin: Address,
+    pub deadline: u64,
+}
+
+#[contractevent(topics = ["admin_transfer_cancelled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminTransferCancelled {}
+
+#[contractevent(topics = ["admin_transferred"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminTransferred {
+    pub previous: Address,
+    #[topic]
+    pub new_admin: Address,
+}
+
+#[contractevent(topics = ["config", "guardian"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GuardianUpdated {
+    pub guardian: Option<Address>,
+}
+
+#[contractevent(topics = ["recovery_initiated"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryInitiated {
+    #[topic]
+    pub guardian: Address,
+    #[topic]
+    pub new_admin: Address,
+    pub deadline: u64,
+}
+
+#[contractevent(topics = ["recovery_cancelled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryCancelled {}
+
+#[contractevent(topics = ["paused"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Paused {
+    pub caller: Address,
+}
+
+#[contractevent(topics = ["unpaused"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Unpaused {
+    pub caller: Address,
+}
+
+#[contractevent(topics = ["upgrade"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Upgraded {
+    pub new_wasm_hash: BytesN<32>,
+}
@@ -0,0 +1,115 @@
+// Internal per-token accounting for PoolContract
+//
+// Every entry point that moves funds records it here, and settlement, fee
+// withdrawal and the status getters read from the same ledger instead of
+// recomputing amounts from counters or live balances.
+//
+// Blend supply is tracked in b-tokens. Every supply records the b-tokens Blend
+// minted for it along with the underlying amount paid in (the cost basis). A
+// withdrawal burns b-tokens and releases the matching share of the cost basis;
+// whatever it paid out above that share is realised yield. The remaining
+// b-tokens valued at the reserve's current `b_rate`, less the remaining cost
+// basis, is unrealised yield.
+
+use soroban_sdk::{contracttype, Address, Env};
+
+use crate::DataKey;
+
+/// Balances the pool owes or holds for one token
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TokenLedger {
+    pub escrowed: i128,       // principal of orders still held
+    pub escrowed_fees: i128,  // fees the orders still held will pay on release
+    pub seller_payable: i128, // seller share of released orders not yet paid out
+    pub fees_owed: i128,      // fees accrued and not yet withdrawn
+    pub supplied: i128,       // underlying supplied to Blend and not yet withdrawn (cost basis)
+    pub b_tokens: i128,       // b-tokens we hold in the Blend reserve
+    pub realized_yield: i128, // interest received through Blend withdrawals
+}
+
+pub fn read_ledger(env: &Env, token: &Address) -> TokenLedger {
+    env.storage()
+        .instance()
+        .get(&DataKey::Ledger(token.clone()))
+        .unwrap_or_default()
+}
+
+/// Whether any funds of `token` have ever been recorded
+pub fn has_ledger(env: &Env, token: &Address) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::Ledger(token.clone()))
+}
+
+fn update(env: &Env, token: &Address, f: impl FnOnce(&mut TokenLedger)) {
+    let mut ledger = read_ledger(env, token);
+    f(&mut ledger);
+    env.storage()
+        .instance()
+        .set(&DataKey::Ledger(token.clone()), &ledger);
+}
+
+/// A buyer deposit now held in escrow, owing `fee` if it is released
+pub fn record_deposit(env: &Env, token: &Address, amount: i128, fee: i128) {
+    update(env, token, |l| {
+        l.escrowed += amount;
+        l.escrowed_fees += fee;
+    });
+}
+
+/// A held order released: the fee is kept and the rest is owed to the seller
+pub fn record_release(env: &Env, token: &Address, amount: i128, fee: i128) {
+    update(env, token, |l| {
+        l.escrowed -= amount;
+        l.escrowed_fees -= fee;
+        l.fees_owed += fee;
+        l.seller_payable += amount - fee;
+    });
+}
+
+/// A held order that would have owed `fee` returned to its buyer in full
+pub fn record_refund(env: &Env, token: &Address, amount: i128, fee: i128) {
+    update(env, token, |l| {
+        l.escrowed -= amount;
+        l.escrowed_fees -= fee;
+    });
+}
+
+/// Seller payables transferred out
+pub fn record_seller_payment(env: &Env, token: &Address, amount: i128) {
+    update(env, token, |l| l.seller_payable -= amount);
+}
+
+/// Platform revenue that is not tied to an order (e.g. claimed emissions)
+pub fn record_fee(env: &Env, token: &Address, amount: i128) {
+    update(env, token, |l| l.fees_owed += amount);
+}
+
+/// Fees transferred out through `withdraw_fees`
+pub fn record_fee_withdrawal(env: &Env, token: &Address, amount: i128) {
+    update(env, token, |l| l.fees_owed -= amount);
+}
+
+/// Record a supply of `amount` that left us holding `b_tokens_after`
+pub fn record_supply(env: &Env, token: &Address, amount: i128, b_tokens_after: i128) {
+    update(env, token, |l| {
+        l.b_tokens = b_tokens_after;
+        l.supplied += amount;
+    });
+}
+
+/// Record a withdrawal that paid out `amount` and left us holding `b_tokens_after`
+pub fn record_withdraw(env: &Env, token: &Address, amount: i128, b_tokens_after: i128) {
+    update(env, token, |l| {
+        let burned = (l.b_tokens - b_tokens_after).clamp(0, l.b_tokens);
+        let cost_released = if l.b_tokens > 0 {
+            l.supplied * burned / l.b_tokens
+        } else {
+            0
+        };
+        l.b_tokens = b_tokens_after;
+        l.supplied -= cost_released;
+        l.realized_yield += amount - cost_released;
+    });
+}
@@ -1,12 +1,16 @@
 #![no_std]
 use soroban_sdk::{
     contract, contractimpl, contracttype, Address, BytesN, Env, Map, Vec, token,
-    contracterror, log, IntoVal
+    contracterror, log, panic_with_error, IntoVal
 };
 
 // Use official Blend SDK for pool integration
 use blend_contract_sdk::pool;
 
+pub use access::Role;
+pub use ledger::TokenLedger;
+pub use reserves::{PendingSupply, ReserveConfig, SupplyMode};
+
 
 // ============================================================
 // POOL CONTRACT TYPES
@@ -17,11 +21,140 @@ use blend_contract_sdk::pool;
 #[repr(u32)]
 pub enum PoolError {
     NotInitialized = 1,
-    AlreadyInitialized = 2,
     Unauthorized = 3,
     InsufficientBalance = 4,
     InvalidAmount = 5,
     BlendOperationFailed = 6,
+    UnsupportedToken = 7,
+    OrderNotFound = 8,
+    OrderNotHeld = 9,
+    EscrowNotExpired = 10,
+    BlendPoolNotConfigured = 11,
+    DuplicateOrder = 12,
+    BlndTokenNotConfigured = 13,
+    EscrowShortfall = 14,
+    FeeTooHigh = 15,
+    NoQueuedChange = 16,
+    TimelockActive = 17,
+    ChangeRequiresTimelock = 18,
+    NoPendingTransfer = 19,
+    TransferExpired = 20,
+    NoGuardian = 21,
+    ContractPaused = 22,
+    NotPaused = 23,
+    MigrationRequired = 24,
+    BlendPoolUnhealthy = 25,
+    NotBlendReserve = 26,
+    UtilizationTooHigh = 27,
+}
+
+/// Fixed-point scalars Blend uses for rates (12 decimals) and config ratios (7 decimals)
+const SCALAR_12: i128 = 1_000_000_000_000;
+const SCALAR_7: i128 = 1_0000000;
+
+/// Default escrow period before a buyer can reclaim an unreleased order (30 days)
+const DEFAULT_ESCROW_PERIOD: u64 = 30 * 24 * 60 * 60;
+
+/// Bounds on the escrow period (1 hour to 1 year)
+const MIN_ESCROW_PERIOD: u64 = 60 * 60;
+const MAX_ESCROW_PERIOD: u64 = 365 * 24 * 60 * 60;
+
+/// Maximum number of records returned by one paginated query
+/// Each record reads an index entry and a deposit entry, so 40 records stay
+/// well inside the network's 100-entry footprint limit
+const MAX_PAGE_SIZE: u32 = 40;
+
+/// Maximum number of index positions one `settle` call works through
+/// Each order adds about three footprint entries on top of a fixed cost per
+/// token; 15 orders across USDC, XLM and two Blend reserves measure at 91 of
+/// the network's 100 entries
+const MAX_SETTLE_BATCH: u32 = 15;
+
+/// Default cap on each side of the fee terms (5%)
+const DEFAULT_MAX_FEE_BPS: u32 = 500;
+
+/// Notice given before a queued fee, seller or Blend pool change can apply (7 days)
+const CONFIG_CHANGE_DELAY: u64 = 7 * 24 * 60 * 60;
+
+/// How long a proposed admin has to accept the transfer (3 days)
+const ADMIN_TRANSFER_WINDOW: u64 = 3 * 24 * 60 * 60;
+
+/// Delay before a guardian-initiated admin rotation completes, during which
+/// the current admin can cancel it (14 days)
+const GUARDIAN_RECOVERY_DELAY: u64 = 14 * 24 * 60 * 60;
+
+/// Lifecycle of an order's escrowed funds
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum DepositStatus {
+    Held = 0,       // Funds escrowed, awaiting release or refund
+    Released = 1,   // Paid out to the seller (minus fee)
+    Refunded = 2,   // Returned to the buyer by the admin
+    Expired = 3,    // Reclaimed by the buyer after the escrow period
+}
+
+/// Fee terms in basis points, locked into each deposit when it is made
+/// 
+/// The buyer pays price + `buyer_surcharge_bps` and the seller receives
+/// price - `seller_commission_bps`; the pool keeps the difference.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeTerms {
+    pub buyer_surcharge_bps: u32,
+    pub seller_commission_bps: u32,
+}
+
+impl FeeTerms {
+    /// Split a deposited amount into (seller_share, fee)
+    pub fn split(&self, amount: i128) -> (i128, i128) {
+        let price = amount * 10000 / (10000 + self.buyer_surcharge_bps as i128);
+        let seller_share = price - price * self.seller_commission_bps as i128 / 10000;
+        (seller_share, amount - seller_share)
+    }
+}
+
+/// Which queued configuration change an operation refers to
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChangeKind {
+    FeeTerms,
+    Seller,
+    BlendPool,
+}
+
+/// A configuration change that only applies after `CONFIG_CHANGE_DELAY`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigChange {
+    FeeTerms(FeeTerms),
+    Seller(Address),
+    BlendPool(Address, Address, u32), // (token, pool, reserve index)
+}
+
+impl ConfigChange {
+    pub fn kind(&self) -> ChangeKind {
+        match self {
+            ConfigChange::FeeTerms(_) => ChangeKind::FeeTerms,
+            ConfigChange::Seller(_) => ChangeKind::Seller,
+            ConfigChange::BlendPool(_, _, _) => ChangeKind::BlendPool,
+        }
+    }
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueuedChange {
+    pub change: ConfigChange,
+    pub effective_at: u64,
+}
+
+/// An admin handover waiting on the new admin (or, for recovery, on its delay)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingAdmin {
+    pub new_admin: Address,
+    pub deadline: u64, // transfer: accept before this time; recovery: complete from this time
 }
 
 /// Deposit record for MSM verification
@@ -33,6 +166,47 @@ pub struct Deposit {
     pub amount: i128,
     pub order_id: u64,
     pub timestamp: u64,
+    pub expires_at: u64,
+    pub status: DepositStatus,
+    pub fee_terms: FeeTerms,
+}
+
+/// Totals for one token, for dashboards
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolStatus {
+    pub token: Address,
+    pub total_deposits: i128,    // principal held in escrow
+    pub fees_earned: i128,       // fees accrued and not yet withdrawn
+    pub supplied_to_blend: bool, // whether this token has shares in Blend
+    pub blend_position: i128,    // current value of our Blend position
+    pub accrued_yield: i128,     // holdings above escrowed principal and fees
+    pub pending_payout: i128,    // seller share of orders still held
+}
+
+/// Interest earned on one token's Blend position, valued at the reserve's current b_rate
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct YieldReport {
+    pub token: Address,
+    pub b_tokens: i128,          // b-tokens we hold
+    pub b_rate: i128,            // underlying per b-token (12 decimals)
+    pub position_value: i128,    // b_tokens valued at b_rate
+    pub cost_basis: i128,        // underlying supplied and not yet withdrawn
+    pub realized_yield: i128,    // interest already received through withdrawals
+    pub unrealized_yield: i128,  // position_value - cost_basis
+}
+
+/// Liabilities against holdings for one token, as reported by `reconcile`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reconciliation {
+    pub token: Address,
+    pub liabilities: i128,     // escrowed principal + seller payables + fees owed
+    pub wallet_balance: i128,  // tokens held by the contract
+    pub blend_position: i128,  // our Blend position valued at the current b_rate
+    pub surplus: i128,         // wallet_balance + blend_position - liabilities
+    pub shortfall: bool,       // holdings do not cover liabilities
 }
 
 #[contracttype]
@@ -40,17 +214,25 @@ pub struct Deposit {
 pub enum DataKey {
     Admin,              // Address - admin who can trigger settlement
     Seller,             // Address - seller to receive settlements
-    FeePercent,         // u32 - fee percentage (e.g., 200 = 2%)
-    BlendPoolUsdc,      // Address - Blend pool for USDC
-    BlendPoolXlm,       // Address - Blend pool for XLM (native wrapper)
-    UsdcToken,          // Address - Circle USDC token address (stays in contract)
-    BlendUsdcToken,     // Address - Blend USDC token address (supplied to Blend pool)
-    TotalDepositsUsdc,  // i128 - total USDC deposited
-    TotalDepositsXlm,   // i128 - total XLM deposited
-    SuppliedToBlend,    // bool - whether funds are currently in Blend
-    FeesEarnedUsdc,     // i128 - accumulated USDC fees
-    FeesEarnedXlm,      // i128 - accumulated XLM fees
-    Deposits,           // Map<u64, Deposit> - Tracking for MSM
+    FeeTerms,           // FeeTerms - buyer surcharge / seller commission for new deposits
+    Deposit(u64),       // Deposit - per-order record for MSM (persistent)
+    DepositIndex(u64),  // u64 - order_id of the n-th deposit (persistent)
+    DepositCount,       // u64 - number of deposits ever recorded
+    SettleCursor,       // u64 - first deposit index that may still be held
+    EscrowPeriod,       // u64 - seconds before a buyer can reclaim a held order
+    Reserves,           // Map<Address, ReserveConfig> - how each token is accepted and supplied
+    BuyerDeposit(Address, u64), // u64 - order_id of a buyer's n-th deposit (persistent)
+    BuyerDepositCount(Address), // u64 - number of deposits made by a buyer (persistent)
+    Ledger(Address),            // TokenLedger - internal accounting per token
+    BlndToken,                  // Address - BLND token Blend pays emissions in
+    MaxFeeBps,                  // u32 - cap on buyer surcharge and seller commission
+    QueuedChange(ChangeKind),   // QueuedChange - pending timelocked config change
+    RoleMember(Role, Address),  // bool - address holds role
+    PendingAdmin,               // PendingAdmin - proposed admin awaiting acceptance
+    Guardian,                   // Address - optional account that can rotate the admin
+    PendingRecovery,            // PendingAdmin - guardian rotation awaiting its delay
+    Paused,                     // bool - deposits and Blend supply suspended
+    PendingSupply(Address),     // PendingSupply - idle balance whose Blend supply failed
 }
 
 // ============================================================
@@ -62,474 +244,1023 @@ pub struct PoolContract;
 
 #[contractimpl]
 impl PoolContract {
-    /// Initialize the pool contract
+    /// Configure the pool at deploy time
+    /// 
+    /// Runs once, atomically with deployment, so nobody can front-run the
+    /// configuration and it cannot be called again afterwards.
     /// 
     /// # Arguments
     /// * `admin` - Address authorized to trigger settlements
     /// * `seller` - Address that receives settlement payouts
-    /// * `fee_percent` - Fee percentage in basis points (200 = 2%)
-    /// * `usdc_token` - USDC token contract address
-    pub fn initialize(
+    /// * `buyer_surcharge_bps` - Added to the price the buyer pays (100 = 1%)
+    /// * `seller_commission_bps` - Taken from the price the seller receives (100 = 1%)
+    /// * `tokens` - Tokens accepted for payment (e.g. USDC and the native XLM SAC),
+    ///   kept in the wallet until `set_reserve` gives them a Blend pool
+    pub fn __constructor(
         env: Env,
         admin: Address,
         seller: Address,
-        fee_percent: u32,
-        usdc_token: Address,
-    ) {
-        if env.storage().instance().has(&DataKey::Admin) {
-            panic!("Already initialized");
-        }
-        
+        buyer_surcharge_bps: u32,
+        seller_commission_bps: u32,
+        tokens: Vec<Address>,
+    ) -> Result<(), PoolError> {
         env.storage().instance().set(&DataKey::Admin, &admin);
         env.storage().instance().set(&DataKey::Seller, &seller);
-        env.storage().instance().set(&DataKey::FeePercent, &fee_percent);
-        env.storage().instance().set(&DataKey::UsdcToken, &usdc_token);
-        env.storage().instance().set(&DataKey::TotalDepositsUsdc, &0i128);
-        env.storage().instance().set(&DataKey::TotalDepositsXlm, &0i128);
-        env.storage().instance().set(&DataKey::SuppliedToBlend, &false);
-        env.storage().instance().set(&DataKey::FeesEarnedUsdc, &0i128);
-        env.storage().instance().set(&DataKey::FeesEarnedXlm, &0i128);
+        env.storage().instance().set(&DataKey::MaxFeeBps, &DEFAULT_MAX_FEE_BPS);
+        let fee_terms = FeeTerms { buyer_surcharge_bps, seller_commission_bps };
+        Self::check_fee_terms(&env, &fee_terms)?;
+        env.storage().instance().set(&DataKey::FeeTerms, &fee_terms);
+        for token_addr in tokens.iter() {
+            let decimals = token::Client::new(&env, &token_addr).decimals();
+            reserves::write_reserve(&env, &token_addr, &ReserveConfig::wallet_only(decimals));
+        }
+        env.storage().instance().set(&DataKey::EscrowPeriod, &DEFAULT_ESCROW_PERIOD);
+        
+        // The admin starts with every role and hands them out from there
+        for role in access::ALL_ROLES {
+            access::grant(&env, role, &admin);
+        }
+        
+        log!(&env, "Pool initialized: admin={}, seller={}, fees={}bp/{}bp", admin, seller,
+             buyer_surcharge_bps, seller_commission_bps);
+        events::Initialized { admin, seller, fee_terms, tokens }.publish(&env);
         
-        log!(&env, "Pool initialized: admin={}, seller={}, fee={}bp", admin, seller, fee_percent);
+        Ok(())
     }
 
-    /// Set Blend pool addresses (admin only)
-    pub fn set_blend_pools(
+    /// Add a token or update its reserve config (config admin only)
+    /// A token's Blend pool can only be set here while the pool has never held
+    /// any of the token; every other pool change goes through `queue_change`.
+    pub fn set_reserve(
         env: Env,
-        blend_pool_usdc: Address,
-        blend_pool_xlm: Address,
-    ) {
-        Self::require_admin(&env);
+        caller: Address,
+        token: Address,
+        config: ReserveConfig,
+    ) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
         
-        env.storage().instance().set(&DataKey::BlendPoolUsdc, &blend_pool_usdc);
-        env.storage().instance().set(&DataKey::BlendPoolXlm, &blend_pool_xlm);
+        if config.supply_threshold < 0 || config.buffer_bps > 10000 || config.max_util_bps > 10000 {
+            return Err(PoolError::InvalidAmount);
+        }
+        // Moving a token's funds to a different Blend pool (or listing it in one for
+        // the first time) goes through the timelock unless the pool never held any
+        let current_pool = reserves::read_reserve(&env, &token)
+            .and_then(|current| current.blend_pool.map(|pool| (pool, current.reserve_index)));
+        let new_pool = config.blend_pool.clone().map(|pool| (pool, config.reserve_index));
+        if current_pool != new_pool && !Self::is_untouched(&env, &token) {
+            return Err(PoolError::ChangeRequiresTimelock);
+        }
+        // An open position stays in its current mode until `migrate_supply_mode` moves it
+        if let Some(current) = reserves::read_reserve(&env, &token)
+            && current.supply_mode != config.supply_mode
+            && ledger::read_ledger(&env, &token).b_tokens > 0
+        {
+            return Err(PoolError::MigrationRequired);
+        }
+        if let Some(blend_pool) = &config.blend_pool {
+            Self::check_blend_reserve(&env, &token, blend_pool, config.reserve_index)?;
+        }
         
-        log!(&env, "Blend pools set: USDC={}, XLM={}", blend_pool_usdc, blend_pool_xlm);
+        reserves::write_reserve(&env, &token, &config);
+        log!(&env, "Reserve updated: token={}, accepted={}, enabled={}", token, config.accepted, config.enabled);
+        events::ReserveUpdated { token, config }.publish(&env);
+        Ok(())
+    }
+
+    /// Reserve config for a token, if it has one
+    pub fn get_reserve(env: Env, token: Address) -> Option<ReserveConfig> {
+        reserves::read_reserve(&env, &token)
+    }
+
+    /// Reserve config for every token the pool handles
+    pub fn get_reserves(env: Env) -> Map<Address, ReserveConfig> {
+        reserves::read_reserves(&env)
+    }
+
+    /// Idle balance of a token whose Blend supply failed, if any
+    pub fn get_pending_supply(env: Env, token: Address) -> Option<PendingSupply> {
+        reserves::read_pending_supply(&env, &token)
     }
 
     /// Deposit funds for an order (buyer calls directly)
-    /// Transfers tokens from buyer to contract and tracks deposit
+    /// Transfers tokens from buyer to contract and tracks deposit.
+    /// Each order_id can only be paid once, whatever its current status.
     pub fn deposit(
         env: Env,
         buyer: Address,
         token: Address,
         amount: i128,
         order_id: u64,
-    ) {
+    ) -> Result<(), PoolError> {
         buyer.require_auth();
+        Self::require_not_paused(&env)?;
         
         if amount <= 0 {
-            panic!("Invalid amount");
+            return Err(PoolError::InvalidAmount);
+        }
+        
+        if !Self::is_accepted_token(&env, &token) {
+            return Err(PoolError::UnsupportedToken);
+        }
+        
+        if storage::has_deposit(&env, order_id) {
+            return Err(PoolError::DuplicateOrder);
         }
         
         // Transfer tokens FROM buyer TO contract
         let client = token::Client::new(&env, &token);
-        client.transfer(&buyer, &env.current_contract_address(), &amount);
-        
-        // Update total deposits
-        if Self::is_usdc_token(&env, &token) {
-            let mut total: i128 = env.storage().instance()
-                .get(&DataKey::TotalDepositsUsdc).unwrap_or(0);
-            total += amount;
-            env.storage().instance().set(&DataKey::TotalDepositsUsdc, &total);
-            log!(&env, "USDC deposit: amount={}, order={}", amount, order_id);
-        } else {
-            // Assume XLM (native)
-            let mut total: i128 = env.storage().instance()
-                .get(&DataKey::TotalDepositsXlm).unwrap_or(0);
-            total += amount;
-            env.storage().instance().set(&DataKey::TotalDepositsXlm, &total);
-            log!(&env, "XLM deposit: amount={}, order={}", amount, order_id);
-        }
+        client.transfer(&buyer, env.current_contract_address(), &amount);
+        
+        let fee_terms = Self::fee_terms(&env);
+        let (_, fee) = fee_terms.split(amount);
+        ledger::record_deposit(&env, &token, amount, fee);
+        log!(&env, "Deposit: token={}, amount={}, order={}", token, amount, order_id);
         
         // Store deposit record (for MSM verification later)
+        let escrow_period: u64 = env.storage().instance()
+            .get(&DataKey::EscrowPeriod).unwrap_or(DEFAULT_ESCROW_PERIOD);
+        let timestamp = env.ledger().timestamp();
         let deposit = Deposit {
             buyer,
             token,
             amount,
             order_id,
-            timestamp: env.ledger().timestamp(),
+            timestamp,
+            expires_at: timestamp + escrow_period,
+            status: DepositStatus::Held,
+            fee_terms,
         };
         
-        let mut deposits: Map<u64, Deposit> = env.storage().instance()
-            .get(&DataKey::Deposits).unwrap_or(Map::new(&env));
-            
-        deposits.set(order_id, deposit);
-        env.storage().instance().set(&DataKey::Deposits, &deposits);
+        storage::write_deposit(&env, &deposit);
+        storage::index_deposit(&env, order_id);
+        storage::index_buyer_deposit(&env, &deposit.buyer, order_id);
+        
+        events::Deposited {
+            order_id,
+            buyer: deposit.buyer,
+            token: deposit.token,
+            amount,
+            expires_at: deposit.expires_at,
+        }.publish(&env);
         
-        // Auto-supply to Blend immediately after deposit
-        Self::internal_supply_to_blend(&env);
+        // Funds stay in the contract until the keeper's next `supply_to_blend`
+        Ok(())
     }
     
-    /// Internal helper to supply funds to Blend (no auth required - called from deposit)
-    /// Note: Only Blend USDC is supplied to Blend pool. Circle USDC stays in contract.
-    fn internal_supply_to_blend(env: &Env) {
-        let blend_usdc_token: Address = match env.storage().instance().get(&DataKey::BlendUsdcToken) {
-            Some(addr) => addr,
-            None => {
-                // Blend USDC not configured yet — skip USDC supply, still try XLM
-                Self::internal_supply_xlm_to_blend(env);
-                return;
+    /// Supply each token's amount in `amounts` to `pool_addr` in a single submit
+    /// Returns false, leaving the funds in the wallet, if Blend rejects the submit
+    fn try_supply_reserves(env: &Env, pool_addr: &Address, amounts: &Map<Address, i128>) -> bool {
+        let contract_addr = env.current_contract_address();
+        
+        // Pre-authorize the transfers that Blend will make on our behalf
+        let mut auths = Vec::new(env);
+        let mut requests: Vec<pool::Request> = Vec::new(env);
+        for (token_addr, amount) in amounts.iter() {
+            let mode = reserves::read_reserve(env, &token_addr)
+                .map_or(SupplyMode::Supply, |config| config.supply_mode);
+            log!(env, "Supplying {} of {} to Blend pool {}", amount, token_addr, pool_addr);
+            auths.push_back(soroban_sdk::auth::InvokerContractAuthEntry::Contract(
+                soroban_sdk::auth::SubContractInvocation {
+                    context: soroban_sdk::auth::ContractContext {
+                        contract: token_addr.clone(),
+                        fn_name: soroban_sdk::Symbol::new(env, "transfer"),
+                        args: soroban_sdk::vec![
+                            env,
+                            contract_addr.clone().into_val(env),
+                            pool_addr.clone().into_val(env),
+                            amount.into_val(env),
+                        ],
+                    },
+                    sub_invocations: soroban_sdk::vec![env],
+                }
+            ));
+            requests.push_back(pool::Request {
+                request_type: mode.supply_request(),
+                address: token_addr,
+                amount,
+            });
+        }
+        env.authorize_as_current_contract(auths);
+        
+        let blend_client = pool::Client::new(env, pool_addr);
+        let positions = match blend_client.try_submit(
+            &contract_addr,
+            &contract_addr,
+            &contract_addr,
+            &requests,
+        ) {
+            Ok(Ok(positions)) => positions,
+            _ => {
+                log!(env, "Blend pool {} rejected the supply", pool_addr);
+                return false;
             }
         };
         
-        let contract_addr = env.current_contract_address();
+        for (token_addr, amount) in amounts.iter() {
+            reserves::clear_pending_supply(env, &token_addr);
+            let b_tokens = reserves::read_reserve(env, &token_addr)
+                .map_or(0, |config| config.supply_mode.b_tokens(&positions, config.reserve_index));
+            ledger::record_supply(env, &token_addr, amount, b_tokens);
+            events::SuppliedToBlend {
+                token: token_addr,
+                pool: pool_addr.clone(),
+                amount,
+            }.publish(env);
+        }
+        true
+    }
+
+    /// Leave `amount` of a token idle and pending supply after Blend rejected it
+    /// or `supply_health` ruled it out
+    fn record_supply_failure(env: &Env, token: &Address, pool_addr: &Address, amount: i128, error: PoolError) {
+        let pending = reserves::record_supply_failure(env, token, amount, error);
+        log!(env, "Supply of {} of {} pending (attempt {})", amount, token, pending.attempts);
+        events::SupplyFailed {
+            token: token.clone(),
+            pool: pool_addr.clone(),
+            amount,
+            error: pending.error,
+        }.publish(env);
+    }
+
+    /// Supply idle balances to Blend (settler only - the keeper job)
+    /// Every reserve with supply switched on contributes its wallet balance above
+    /// the liquidity buffer once that excess reaches its `supply_threshold` and
+    /// its Blend pool passes `supply_health`.
+    /// Reserves that share a Blend pool go in one submit, so deposits only record
+    /// funds and the cross-contract cost is paid once per batch.
+    /// A batch Blend rejects is retried one reserve at a time; whatever still
+    /// fails stays in the wallet as a `PendingSupply` for the next run.
+    /// Returns the amount supplied per token
+    pub fn supply_to_blend(env: Env, caller: Address) -> Result<Map<Address, i128>, PoolError> {
+        access::require_role(&env, &caller, Role::Settler)?;
+        Self::require_not_paused(&env)?;
         
-        // =========== SUPPLY BLEND USDC ===========
-        // Only Blend USDC goes to the Blend pool (testnet.blend.capital uses Blend USDC)
-        // Circle USDC stays in the contract wallet
-        let blend_pool_usdc: Option<Address> = env.storage().instance()
-            .get(&DataKey::BlendPoolUsdc);
+        let mut batches: Map<Address, Map<Address, i128>> = Map::new(&env);
+        for (token_addr, config) in reserves::read_reserves(&env).iter() {
+            let Some(pool_addr) = config.supply_pool() else {
+                continue;
+            };
+            let excess = Self::wallet_balance(&env, &token_addr) - Self::buffer_target(&env, &token_addr, &config);
+            if excess <= 0 || excess < config.supply_threshold {
+                continue;
+            }
+            if let Err(error) = Self::supply_health(&env, &token_addr, &pool_addr, &config) {
+                Self::record_supply_failure(&env, &token_addr, &pool_addr, excess, error);
+                continue;
+            }
+            let mut batch = batches.get(pool_addr.clone()).unwrap_or(Map::new(&env));
+            batch.set(token_addr.clone(), excess);
+            batches.set(pool_addr, batch);
+        }
         
-        if let Some(pool_addr) = blend_pool_usdc {
-            let usdc_client = token::Client::new(env, &blend_usdc_token);
-            let usdc_balance = usdc_client.balance(&contract_addr);
+        if batches.is_empty() {
+            log!(&env, "Nothing above the supply threshold to supply to Blend");
+        }
+        let mut supplied: Map<Address, i128> = Map::new(&env);
+        for (pool_addr, batch) in batches.iter() {
+            if Self::try_supply_reserves(&env, &pool_addr, &batch) {
+                for (token_addr, amount) in batch.iter() {
+                    supplied.set(token_addr, amount);
+                }
+                continue;
+            }
             
-            if usdc_balance > 0 {
-                log!(env, "Supplying {} Blend USDC to Blend pool {}", usdc_balance, pool_addr);
-                
-                // Pre-authorize the transfer that Blend will make on our behalf
-                env.authorize_as_current_contract(soroban_sdk::vec![
-                    env,
-                    soroban_sdk::auth::InvokerContractAuthEntry::Contract(
-                        soroban_sdk::auth::SubContractInvocation {
-                            context: soroban_sdk::auth::ContractContext {
-                                contract: blend_usdc_token.clone(),
-                                fn_name: soroban_sdk::Symbol::new(env, "transfer"),
-                                args: soroban_sdk::vec![
-                                    env,
-                                    contract_addr.clone().into_val(env),
-                                    pool_addr.clone().into_val(env),
-                                    usdc_balance.into_val(env),
-                                ],
-                            },
-                            sub_invocations: soroban_sdk::vec![env],
-                        }
-                    )
-                ]);
-                
-                let mut requests: Vec<pool::Request> = Vec::new(env);
-                requests.push_back(pool::Request {
-                    request_type: 2, // SupplyCollateral
-                    address: blend_usdc_token.clone(),
-                    amount: usdc_balance,
-                });
-                
-                let blend_client = pool::Client::new(env, &pool_addr);
-                
-                // Authorize Blend to transfer Blend USDC from this contract
-                env.authorize_as_current_contract(soroban_sdk::vec![
-                    env,
-                    soroban_sdk::auth::InvokerContractAuthEntry::Contract(
-                        soroban_sdk::auth::SubContractInvocation {
-                            context: soroban_sdk::auth::ContractContext {
-                                contract: blend_usdc_token.clone(),
-                                fn_name: soroban_sdk::Symbol::new(env, "transfer"),
-                                args: soroban_sdk::vec![
-                                    env,
-                                    contract_addr.clone().into_val(env),
-                                    pool_addr.clone().into_val(env),
-                                    usdc_balance.into_val(env),
-                                ],
-                            },
-                            sub_invocations: soroban_sdk::vec![env],
-                        }
-                    )
-                ]);
-
-                blend_client.submit(
-                    &contract_addr,
-                    &contract_addr,
-                    &contract_addr,
-                    &requests,
-                );
-                
-                log!(env, "Successfully supplied Blend USDC to Blend pool!");
+            // One reserve the pool rejects (e.g. over its supply cap) must not hold back the rest
+            for (token_addr, amount) in batch.iter() {
+                let single = Map::from_array(&env, [(token_addr.clone(), amount)]);
+                if batch.len() > 1 && Self::try_supply_reserves(&env, &pool_addr, &single) {
+                    supplied.set(token_addr, amount);
+                } else {
+                    Self::record_supply_failure(&env, &token_addr, &pool_addr, amount, PoolError::BlendOperationFailed);
+                }
             }
         }
         
-        // =========== SUPPLY XLM (Wrapped) ===========
-        Self::internal_supply_xlm_to_blend(env);
+        Ok(supplied)
+    }
+
+    /// Move a token's wallet balance back to its liquidity buffer (settler only)
+    /// Withdraws from Blend when the wallet is below the buffer and supplies the
+    /// excess when it is above (subject to the reserve's `supply_threshold`).
+    /// A supply Blend rejects is left pending and counts as nothing moved.
+    /// Returns the amount moved: positive if supplied, negative if withdrawn
+    pub fn rebalance(env: Env, caller: Address, token: Address) -> Result<i128, PoolError> {
+        access::require_role(&env, &caller, Role::Settler)?;
+        Self::require_not_paused(&env)?;
+        
+        let config = reserves::read_reserve(&env, &token).ok_or(PoolError::UnsupportedToken)?;
+        if config.blend_pool.is_none() {
+            return Err(PoolError::BlendPoolNotConfigured);
+        }
+        
+        let balance = Self::wallet_balance(&env, &token);
+        let target = Self::buffer_target(&env, &token, &config);
+        if balance < target {
+            let new_balance = Self::ensure_liquidity(&env, &token, target);
+            log!(&env, "Rebalanced {}: withdrew {} to refill the buffer", token, new_balance - balance);
+            return Ok(balance - new_balance);
+        }
         
-        env.storage().instance().set(&DataKey::SuppliedToBlend, &true);
+        let excess = balance - target;
+        if let Some(pool_addr) = config.supply_pool()
+            && excess > 0
+            && excess >= config.supply_threshold
+        {
+            if let Err(error) = Self::supply_health(&env, &token, &pool_addr, &config) {
+                Self::record_supply_failure(&env, &token, &pool_addr, excess, error);
+                return Ok(0);
+            }
+            if !Self::try_supply_reserves(&env, &pool_addr, &Map::from_array(&env, [(token.clone(), excess)])) {
+                Self::record_supply_failure(&env, &token, &pool_addr, excess, PoolError::BlendOperationFailed);
+                return Ok(0);
+            }
+            log!(&env, "Rebalanced {}: supplied {} above the buffer", token, excess);
+            return Ok(excess);
+        }
+        
+        Ok(0)
     }
 
-    /// Internal helper to supply XLM to Blend pool
-    fn internal_supply_xlm_to_blend(env: &Env) {
+    /// Move a reserve's Blend position into `mode` (config admin only)
+    /// Withdraws everything the contract holds in the other position, e.g. the
+    /// SupplyCollateral positions opened before plain Supply became the default,
+    /// supplies it back as `mode` and records `mode` on the reserve. Refused
+    /// while paused or while the pool could not take the funds back.
+    /// Returns the amount moved
+    pub fn migrate_supply_mode(
+        env: Env,
+        caller: Address,
+        token: Address,
+        mode: SupplyMode,
+    ) -> Result<i128, PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
+        Self::require_not_paused(&env)?;
+        
+        let mut config = reserves::read_reserve(&env, &token).ok_or(PoolError::UnsupportedToken)?;
+        let pool_addr = config.blend_pool.clone().ok_or(PoolError::BlendPoolNotConfigured)?;
+        let from = match mode {
+            SupplyMode::Supply => SupplyMode::Collateral,
+            SupplyMode::Collateral => SupplyMode::Supply,
+        };
+        
+        let blend_client = pool::Client::new(&env, &pool_addr);
+        let reserve = blend_client.get_reserve(&token);
+        let positions = blend_client.get_positions(&env.current_contract_address());
+        let b_tokens = from.b_tokens(&positions, reserve.config.index);
+        // Check before withdrawing that the funds can go straight back in
+        if b_tokens > 0 {
+            Self::supply_health(&env, &token, &pool_addr, &config)?;
+        }
+        
+        config.supply_mode = mode;
+        reserves::write_reserve(&env, &token, &config);
+        
+        let mut moved = 0;
+        if b_tokens > 0 {
+            // Round up so the withdrawal burns every b-token in the old position
+            let value = (b_tokens * reserve.data.b_rate + SCALAR_12 - 1) / SCALAR_12;
+            let balance = Self::wallet_balance(&env, &token);
+            moved = Self::withdraw_position(&env, &token, &pool_addr, from, reserve.config.index, value)? - balance;
+            if !Self::try_supply_reserves(&env, &pool_addr, &Map::from_array(&env, [(token.clone(), moved)])) {
+                return Err(PoolError::BlendOperationFailed);
+            }
+        }
+        
+        log!(&env, "Migrated {} of {} to supply mode {}", moved, token, mode);
+        events::ReserveUpdated { token, config }.publish(&env);
+        Ok(moved)
+    }
+
+    // ============================================================
+    // EMERGENCY CONTROLS
+    // ============================================================
+
+    /// Suspend deposits and Blend supply (pauser only)
+    /// Releases, refunds, reclaims and settlement keep working so buyers and
+    /// the seller can still be paid out while the pool is paused.
+    pub fn pause(env: Env, caller: Address) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::Pauser)?;
+        env.storage().instance().set(&DataKey::Paused, &true);
+        log!(&env, "Pool paused by {}", caller);
+        events::Paused { caller }.publish(&env);
+        Ok(())
+    }
+
+    /// Resume deposits and Blend supply (pauser only)
+    pub fn unpause(env: Env, caller: Address) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::Pauser)?;
+        env.storage().instance().remove(&DataKey::Paused);
+        log!(&env, "Pool unpaused by {}", caller);
+        events::Unpaused { caller }.publish(&env);
+        Ok(())
+    }
+
+    /// Whether deposits and Blend supply are suspended
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    /// Pull every Blend position back into the contract wallet (pauser only)
+    /// Only allowed while paused, so the next deposit can't re-supply the funds.
+    /// Each withdrawal is capped by the reserve's free liquidity; call again
+    /// once borrowers repay if anything is left behind. A reserve whose pool
+    /// rejects the withdrawal is skipped with a `WithdrawFailed` event so the
+    /// others still come out.
+    /// Returns the amount withdrawn per token
+    pub fn emergency_withdraw_from_blend(env: Env, caller: Address) -> Result<Map<Address, i128>, PoolError> {
+        access::require_role(&env, &caller, Role::Pauser)?;
+        if !Self::is_paused(env.clone()) {
+            return Err(PoolError::NotPaused);
+        }
+        
         let contract_addr = env.current_contract_address();
+        let mut withdrawn: Map<Address, i128> = Map::new(&env);
+        for token_addr in reserves::read_reserves(&env).keys().iter() {
+            if Self::blend_position_value(&env, &token_addr) > 0 {
+                let before = token::Client::new(&env, &token_addr).balance(&contract_addr);
+                match Self::try_ensure_liquidity(&env, &token_addr, i128::MAX) {
+                    Ok(after) => {
+                        log!(&env, "Emergency Blend withdrawal: {} of {}", after - before, token_addr);
+                        withdrawn.set(token_addr, after - before);
+                    }
+                    Err(error) => {
+                        log!(&env, "Emergency Blend withdrawal of {} failed", token_addr);
+                        events::WithdrawFailed {
+                            pool: Self::blend_pool_for(&env, &token_addr).unwrap(),
+                            token: token_addr,
+                            error: error as u32,
+                        }.publish(&env);
+                    }
+                }
+            }
+        }
         
-        let blend_pool_xlm: Option<Address> = env.storage().instance()
-            .get(&DataKey::BlendPoolXlm);
+        Ok(withdrawn)
+    }
+
+    /// Release a single held order to the seller (settler only)
+    /// Pays the order amount minus fees and marks the deposit Released
+    /// Returns the seller's share
+    pub fn release(env: Env, caller: Address, order_id: u64) -> Result<i128, PoolError> {
+        access::require_role(&env, &caller, Role::Settler)?;
         
-        if let Some(pool_addr) = blend_pool_xlm {
-            // XLM on Soroban is wrapped XLM (SAC)
-            // Use the native XLM SAC address for testnet
-            // CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC
-            let xlm_sac = soroban_sdk::Address::from_string(
-                &soroban_sdk::String::from_str(env, "CDLZFC3SYJYDZT7K67VZ75HPJVIEUVNIXF47ZG2FB2RMQQVU2HHGCYSC")
-            );
-            
-            let xlm_client = token::Client::new(env, &xlm_sac);
-            let xlm_balance = xlm_client.balance(&contract_addr);
-            
-            if xlm_balance > 0 {
-                log!(env, "Supplying {} XLM to Blend pool {}", xlm_balance, pool_addr);
-                
-                // Pre-authorize the transfer that Blend will make on our behalf
-                env.authorize_as_current_contract(soroban_sdk::vec![
-                    env,
-                    soroban_sdk::auth::InvokerContractAuthEntry::Contract(
-                        soroban_sdk::auth::SubContractInvocation {
-                            context: soroban_sdk::auth::ContractContext {
-                                contract: xlm_sac.clone(),
-                                fn_name: soroban_sdk::Symbol::new(env, "transfer"),
-                                args: soroban_sdk::vec![
-                                    env,
-                                    contract_addr.clone().into_val(env),
-                                    pool_addr.clone().into_val(env),
-                                    xlm_balance.into_val(env),
-                                ],
-                            },
-                            sub_invocations: soroban_sdk::vec![env],
-                        }
-                    )
-                ]);
-                
-                let mut requests: Vec<pool::Request> = Vec::new(env);
-                requests.push_back(pool::Request {
-                    request_type: 2, // SupplyCollateral
-                    address: xlm_sac.clone(),
-                    amount: xlm_balance,
-                });
-                
-                let blend_client = pool::Client::new(env, &pool_addr);
-                blend_client.submit(
-                    &contract_addr,
-                    &contract_addr,
-                    &contract_addr,
-                    &requests,
-                );
-                
-                log!(env, "Successfully supplied XLM to Blend pool!");
+        let seller: Address = env.storage().instance().get(&DataKey::Seller)
+            .ok_or(PoolError::NotInitialized)?;
+        let mut deposit = Self::load_held_deposit(&env, order_id)?;
+        
+        let fee = Self::close_deposit(&env, &mut deposit, DepositStatus::Released);
+        let seller_share = deposit.amount - fee;
+        
+        if seller_share > 0 {
+            if Self::ensure_liquidity(&env, &deposit.token, seller_share) < seller_share {
+                return Err(PoolError::InsufficientBalance);
             }
+            let client = token::Client::new(&env, &deposit.token);
+            client.transfer(&env.current_contract_address(), &seller, &seller_share);
+            ledger::record_seller_payment(&env, &deposit.token, seller_share);
         }
+        
+        log!(&env, "Released order {}: {} to seller, fee={}", order_id, seller_share, fee);
+        events::Released { order_id, seller, token: deposit.token, seller_share, fee }.publish(&env);
+        
+        Ok(seller_share)
     }
 
-    /// Supply funds to Blend pool to earn yield
-    /// Called periodically (e.g., after deposits accumulate)
-    /// Note: Only Blend USDC and XLM are supplied. Circle USDC stays in contract.
-    pub fn supply_to_blend(env: Env) {
-        Self::require_admin(&env);
+    /// Refund a single held order to its buyer (settler only)
+    /// Used for WooCommerce cancellations; the full amount is returned without fees
+    pub fn refund(env: Env, caller: Address, order_id: u64) -> Result<i128, PoolError> {
+        access::require_role(&env, &caller, Role::Settler)?;
         
-        let total_usdc: i128 = env.storage().instance()
-            .get(&DataKey::TotalDepositsUsdc).unwrap_or(0);
-        let total_xlm: i128 = env.storage().instance()
-            .get(&DataKey::TotalDepositsXlm).unwrap_or(0);
+        let mut deposit = Self::load_held_deposit(&env, order_id)?;
+        Self::close_deposit(&env, &mut deposit, DepositStatus::Refunded);
         
-        // Only supply if we have meaningful balance
-        if total_usdc < 1_0000000 && total_xlm < 10_0000000 {
-            log!(&env, "Insufficient balance to supply to Blend");
-            return;
+        if Self::ensure_liquidity(&env, &deposit.token, deposit.amount) < deposit.amount {
+            return Err(PoolError::InsufficientBalance);
         }
+        let client = token::Client::new(&env, &deposit.token);
+        client.transfer(&env.current_contract_address(), &deposit.buyer, &deposit.amount);
         
-        // Supply Blend USDC (not Circle USDC) to Blend pool
-        let blend_pool_usdc: Option<Address> = env.storage().instance()
-            .get(&DataKey::BlendPoolUsdc);
+        log!(&env, "Refunded order {}: {} to buyer {}", order_id, deposit.amount, deposit.buyer);
+        events::Refunded {
+            order_id,
+            buyer: deposit.buyer,
+            token: deposit.token,
+            amount: deposit.amount,
+        }.publish(&env);
         
-        if let Some(pool) = blend_pool_usdc {
-            if total_usdc > 0 {
-                log!(&env, "Supplying Blend USDC to Blend pool {}", pool);
-                env.storage().instance().set(&DataKey::SuppliedToBlend, &true);
-            }
+        Ok(deposit.amount)
+    }
+
+    /// Reclaim an order's funds after its escrow period has expired (buyer only)
+    /// Lets the buyer recover funds the admin never released or refunded
+    pub fn reclaim(env: Env, order_id: u64) -> Result<i128, PoolError> {
+        let mut deposit = Self::load_held_deposit(&env, order_id)?;
+        deposit.buyer.require_auth();
+        
+        if env.ledger().timestamp() < deposit.expires_at {
+            return Err(PoolError::EscrowNotExpired);
+        }
+        
+        Self::close_deposit(&env, &mut deposit, DepositStatus::Expired);
+        
+        if Self::ensure_liquidity(&env, &deposit.token, deposit.amount) < deposit.amount {
+            return Err(PoolError::InsufficientBalance);
         }
+        let client = token::Client::new(&env, &deposit.token);
+        client.transfer(&env.current_contract_address(), &deposit.buyer, &deposit.amount);
         
-        log!(&env, "Supply to Blend complete: USDC={}, XLM={}", total_usdc, total_xlm);
+        log!(&env, "Buyer reclaimed expired order {}: {}", order_id, deposit.amount);
+        events::Reclaimed {
+            order_id,
+            buyer: deposit.buyer,
+            token: deposit.token,
+            amount: deposit.amount,
+        }.publish(&env);
+        
+        Ok(deposit.amount)
     }
 
     /// Withdraw from Blend and settle to seller
-    /// Called at end-of-day by admin/backend
-    /// Transfers both Circle USDC and Blend USDC balances to seller (minus fees)
-    pub fn settle(env: Env) -> (i128, i128) {
-        Self::require_admin(&env);
+    /// Called at end-of-day by the backend (settler only)
+    /// Releases held orders to the seller (minus fees) in one transfer per token,
+    /// oldest first, looking at no more than `limit` deposits (capped at
+    /// MAX_SETTLE_BATCH) so a backlog is worked through over several calls.
+    /// Seller shares are redeemed from Blend first; orders that the available
+    /// liquidity cannot cover stay Held for `release` once liquidity returns,
+    /// and the batch moves on so they never hold up later orders.
+    /// Returns the amount paid to the seller per token
+    pub fn settle(env: Env, caller: Address, limit: u32) -> Result<Map<Address, i128>, PoolError> {
+        access::require_role(&env, &caller, Role::Settler)?;
         
         let seller: Address = env.storage().instance().get(&DataKey::Seller)
-            .expect("Not initialized");
-        let fee_percent: u32 = env.storage().instance().get(&DataKey::FeePercent)
-            .unwrap_or(200); // Default 2%
+            .ok_or(PoolError::NotInitialized)?;
         let contract_addr = env.current_contract_address();
         
-        let total_usdc: i128 = env.storage().instance()
-            .get(&DataKey::TotalDepositsUsdc).unwrap_or(0);
-        let total_xlm: i128 = env.storage().instance()
-            .get(&DataKey::TotalDepositsXlm).unwrap_or(0);
-            
-        // Calculate fees (e.g., 2% = 200 basis points)
-        let fee_usdc = (total_usdc * fee_percent as i128) / 10000;
-        let fee_xlm = (total_xlm * fee_percent as i128) / 10000;
-        
-        // Seller gets the rest (98%)
-        let seller_usdc = total_usdc - fee_usdc;
-        let seller_xlm = total_xlm - fee_xlm;
-        
-        // Withdraw ONLY seller's share from Blend if supplied
-        let supplied: bool = env.storage().instance()
-            .get(&DataKey::SuppliedToBlend).unwrap_or(false);
-        
-        if supplied {
-            log!(&env, "Withdrawing seller share from Blend: USDC={}, XLM={}", seller_usdc, seller_xlm);
-        }
-        
-        // Transfer Circle USDC to seller (held in contract wallet)
-        if let Some(circle_usdc) = env.storage().instance().get::<_, Address>(&DataKey::UsdcToken) {
-            let client = token::Client::new(&env, &circle_usdc);
-            let balance = client.balance(&contract_addr);
-            if balance > 0 {
-                let fee = (balance * fee_percent as i128) / 10000;
-                let seller_share = balance - fee;
-                if seller_share > 0 {
-                    client.transfer(&contract_addr, &seller, &seller_share);
-                    log!(&env, "Settled {} Circle USDC to seller", seller_share);
-                }
+        // Held orders in this batch
+        let start = storage::settle_cursor(&env);
+        let end = storage::deposit_count(&env).min(start + limit.min(MAX_SETTLE_BATCH) as u64);
+        let mut held: Vec<Deposit> = Vec::new(&env);
+        for index in start..end {
+            if let Some(order_id) = storage::deposit_id_at(&env, index)
+                && let Some(deposit) = storage::read_deposit(&env, order_id)
+                && deposit.status == DepositStatus::Held
+            {
+                held.push_back(deposit);
             }
         }
         
-        // Transfer Blend USDC to seller (withdrawn from Blend or in balance)
-        if let Some(blend_usdc) = env.storage().instance().get::<_, Address>(&DataKey::BlendUsdcToken) {
-            let client = token::Client::new(&env, &blend_usdc);
-            let balance = client.balance(&contract_addr);
-            if balance > 0 {
-                let fee = (balance * fee_percent as i128) / 10000;
-                let seller_share = balance - fee;
-                if seller_share > 0 {
-                    client.transfer(&contract_addr, &seller, &seller_share);
-                    log!(&env, "Settled {} Blend USDC to seller", seller_share);
-                }
-            }
+        // Seller share needed per token for every held order
+        let mut needed: Map<Address, i128> = Map::new(&env);
+        for deposit in held.iter() {
+            let (seller_share, _) = deposit.fee_terms.split(deposit.amount);
+            let pending = needed.get(deposit.token.clone()).unwrap_or(0);
+            needed.set(deposit.token, pending + seller_share);
+        }
+        // Plus anything released earlier that is still owed to the seller
+        for (token_addr, amount) in needed.clone().iter() {
+            needed.set(token_addr.clone(), amount + ledger::read_ledger(&env, &token_addr).seller_payable);
         }
         
-        // For XLM, we'd need to use the native asset wrapper or Stellar operations
-        if seller_xlm > 0 {
-            // TODO: Handle native XLM transfer
-            log!(&env, "Settled {} XLM to seller (pending native handling)", seller_xlm);
+        // Withdraw the seller shares from Blend where the wallet falls short
+        let mut liquid: Map<Address, i128> = Map::new(&env);
+        for (token_addr, amount) in needed.iter() {
+            let available = Self::ensure_liquidity(&env, &token_addr, amount);
+            liquid.set(token_addr, available);
         }
         
-        // Update fee trackers
-        let mut fees_usdc: i128 = env.storage().instance()
-            .get(&DataKey::FeesEarnedUsdc).unwrap_or(0);
-        fees_usdc += fee_usdc;
-        env.storage().instance().set(&DataKey::FeesEarnedUsdc, &fees_usdc);
+        let mut deferred: u32 = 0;
         
-        let mut fees_xlm: i128 = env.storage().instance()
-            .get(&DataKey::FeesEarnedXlm).unwrap_or(0);
-        fees_xlm += fee_xlm;
-        env.storage().instance().set(&DataKey::FeesEarnedXlm, &fees_xlm);
+        let mut fees: Map<Address, i128> = Map::new(&env);
         
-        // Reset deposit counters (since we've processed this batch)
-        env.storage().instance().set(&DataKey::TotalDepositsUsdc, &0i128);
-        env.storage().instance().set(&DataKey::TotalDepositsXlm, &0i128);
+        for mut deposit in held.iter() {
+            let order_id = deposit.order_id;
+            let available = liquid.get(deposit.token.clone()).unwrap_or(0);
+            let (seller_share, _) = deposit.fee_terms.split(deposit.amount);
+            if seller_share > available {
+                log!(&env, "Insufficient liquidity for order {}, leaving it for release", order_id);
+                events::SettlementDeferred { order_id, token: deposit.token, seller_share }.publish(&env);
+                deferred += 1;
+                continue;
+            }
+            liquid.set(deposit.token.clone(), available - seller_share);
+            
+            let fee = Self::close_deposit(&env, &mut deposit, DepositStatus::Released);
+            events::Released {
+                order_id,
+                seller: seller.clone(),
+                token: deposit.token.clone(),
+                seller_share,
+                fee,
+            }.publish(&env);
+            
+            fees.set(deposit.token.clone(), fees.get(deposit.token).unwrap_or(0) + fee);
+        }
+        storage::write_settle_cursor(&env, end);
         
-        log!(&env, "Settlement complete: seller shares paid, fees retained in pool. USDC_FEE={}, XLM_FEE={}", 
-             fee_usdc, fee_xlm);
+        // Pay each token's seller payables in one transfer, as far as liquidity allows
+        let mut paid: Map<Address, i128> = Map::new(&env);
+        for token_addr in needed.keys().iter() {
+            let client = token::Client::new(&env, &token_addr);
+            let payable = ledger::read_ledger(&env, &token_addr).seller_payable
+                .min(client.balance(&contract_addr));
+            if payable <= 0 {
+                continue;
+            }
+            client.transfer(&contract_addr, &seller, &payable);
+            ledger::record_seller_payment(&env, &token_addr, payable);
+            log!(&env, "Settled {} of {} to seller", payable, token_addr);
+            paid.set(token_addr, payable);
+        }
         
-        (seller_usdc, seller_xlm)
+        log!(&env, "Settlement complete: seller shares paid, fees retained in pool");
+        events::Settled {
+            seller,
+            paid: paid.clone(),
+            fees,
+            orders_released: held.len() - deferred,
+            orders_deferred: deferred,
+        }.publish(&env);
+        
+        Ok(paid)
     }
 
-    /// Get current pool status
-    pub fn get_status(env: Env) -> (i128, i128, i128, i128, bool) {
-        let total_usdc: i128 = env.storage().instance()
-            .get(&DataKey::TotalDepositsUsdc).unwrap_or(0);
-        let total_xlm: i128 = env.storage().instance()
-            .get(&DataKey::TotalDepositsXlm).unwrap_or(0);
-        let fees_usdc: i128 = env.storage().instance()
-            .get(&DataKey::FeesEarnedUsdc).unwrap_or(0);
-        let fees_xlm: i128 = env.storage().instance()
-            .get(&DataKey::FeesEarnedXlm).unwrap_or(0);
-        let supplied: bool = env.storage().instance()
-            .get(&DataKey::SuppliedToBlend).unwrap_or(false);
+    /// Get current pool status for one token
+    pub fn get_status(env: Env, token: Address) -> PoolStatus {
+        let ledger = ledger::read_ledger(&env, &token);
+        
+        let wallet = token::Client::new(&env, &token).balance(&env.current_contract_address());
+        let blend_position = Self::blend_position_value(&env, &token);
         
-        (total_usdc, total_xlm, fees_usdc, fees_xlm, supplied)
+        PoolStatus {
+            total_deposits: ledger.escrowed,
+            fees_earned: ledger.fees_owed,
+            supplied_to_blend: ledger.b_tokens > 0,
+            blend_position,
+            accrued_yield: (wallet + blend_position - Self::liabilities(&ledger)).max(0),
+            pending_payout: ledger.seller_payable + ledger.escrowed - ledger.escrowed_fees,
+            token,
+        }
     }
 
-    /// Withdraw accumulated fees (admin only)
-    /// Transfers fee balances of both Circle USDC and Blend USDC
-    pub fn withdraw_fees(env: Env, recipient: Address) -> (i128, i128) {
-        Self::require_admin(&env);
+    /// Realised and unrealised Blend yield for a supplied token
+    pub fn get_yield(env: Env, token: Address) -> Result<YieldReport, PoolError> {
+        let pool_addr = Self::blend_pool_for(&env, &token)
+            .ok_or(PoolError::BlendPoolNotConfigured)?;
+        let reserve = pool::Client::new(&env, &pool_addr).get_reserve(&token);
+        
+        let ledger = ledger::read_ledger(&env, &token);
+        let position_value = ledger.b_tokens * reserve.data.b_rate / SCALAR_12;
         
+        Ok(YieldReport {
+            token,
+            b_tokens: ledger.b_tokens,
+            b_rate: reserve.data.b_rate,
+            position_value,
+            cost_basis: ledger.supplied,
+            realized_yield: ledger.realized_yield,
+            unrealized_yield: position_value - ledger.supplied,
+        })
+    }
+
+    /// Check every configured token's holdings against what the pool owes
+    pub fn reconcile(env: Env) -> Vec<Reconciliation> {
         let contract_addr = env.current_contract_address();
+        let mut tokens: Vec<Address> = reserves::read_reserves(&env).keys();
+        if let Some(blnd) = env.storage().instance().get::<_, Address>(&DataKey::BlndToken)
+            && !tokens.contains(&blnd)
+        {
+            tokens.push_back(blnd);
+        }
+        
+        let mut report: Vec<Reconciliation> = Vec::new(&env);
+        for token in tokens.iter() {
+            let liabilities = Self::liabilities(&ledger::read_ledger(&env, &token));
+            let wallet_balance = token::Client::new(&env, &token).balance(&contract_addr);
+            let blend_position = Self::blend_position_value(&env, &token);
+            let surplus = wallet_balance + blend_position - liabilities;
+            report.push_back(Reconciliation {
+                token,
+                liabilities,
+                wallet_balance,
+                blend_position,
+                surplus,
+                shortfall: surplus < 0,
+            });
+        }
+        
+        report
+    }
+
+    /// Look up a single deposit by order id
+    pub fn get_deposit(env: Env, order_id: u64) -> Result<Deposit, PoolError> {
+        storage::read_deposit(&env, order_id).ok_or(PoolError::OrderNotFound)
+    }
+
+    /// Number of deposits ever recorded (the upper bound for `get_deposits` cursors)
+    pub fn get_deposit_count(env: Env) -> u64 {
+        storage::deposit_count(&env)
+    }
+
+    /// Page through deposit records in the order they were made
+    /// 
+    /// # Arguments
+    /// * `cursor` - Index of the first deposit to return (0 = oldest)
+    /// * `limit` - Maximum number of records to return (capped at 40)
+    pub fn get_deposits(env: Env, cursor: u64, limit: u32) -> Vec<Deposit> {
+        let mut page: Vec<Deposit> = Vec::new(&env);
+        let end = cursor
+            .saturating_add(limit.min(MAX_PAGE_SIZE) as u64)
+            .min(storage::deposit_count(&env));
         
-        let fees_usdc: i128 = env.storage().instance()
-            .get(&DataKey::FeesEarnedUsdc).unwrap_or(0);
-        let fees_xlm: i128 = env.storage().instance()
-            .get(&DataKey::FeesEarnedXlm).unwrap_or(0);
-        
-        // Transfer Circle USDC fees
-        if let Some(circle_usdc) = env.storage().instance().get::<_, Address>(&DataKey::UsdcToken) {
-            let client = token::Client::new(&env, &circle_usdc);
-            let balance = client.balance(&contract_addr);
-            if balance > 0 {
-                client.transfer(&contract_addr, &recipient, &balance);
-                log!(&env, "Withdrew {} Circle USDC fees", balance);
+        for index in cursor..end {
+            if let Some(deposit) = storage::deposit_id_at(&env, index)
+                .and_then(|order_id| storage::read_deposit(&env, order_id))
+            {
+                page.push_back(deposit);
             }
         }
         
-        // Transfer Blend USDC fees
-        if let Some(blend_usdc) = env.storage().instance().get::<_, Address>(&DataKey::BlendUsdcToken) {
-            let client = token::Client::new(&env, &blend_usdc);
-            let balance = client.balance(&contract_addr);
-            if balance > 0 {
-                client.transfer(&contract_addr, &recipient, &balance);
-                log!(&env, "Withdrew {} Blend USDC fees", balance);
+        page
+    }
+
+    /// Page through one buyer's deposits in the order they were made
+    /// 
+    /// # Arguments
+    /// * `buyer` - Buyer whose deposits to list
+    /// * `cursor` - Index into the buyer's deposits of the first record to return (0 = oldest)
+    /// * `limit` - Maximum number of records to return (capped at 40)
+    pub fn get_deposits_by_buyer(env: Env, buyer: Address, cursor: u64, limit: u32) -> Vec<Deposit> {
+        let mut page: Vec<Deposit> = Vec::new(&env);
+        let end = cursor
+            .saturating_add(limit.min(MAX_PAGE_SIZE) as u64)
+            .min(storage::buyer_deposit_count(&env, &buyer));
+        
+        for index in cursor..end {
+            if let Some(deposit) = storage::buyer_deposit_id_at(&env, &buyer, index)
+                .and_then(|order_id| storage::read_deposit(&env, order_id))
+            {
+                page.push_back(deposit);
             }
         }
         
-        // Reset fee counters
-        env.storage().instance().set(&DataKey::FeesEarnedUsdc, &0i128);
-        env.storage().instance().set(&DataKey::FeesEarnedXlm, &0i128);
+        page
+    }
+
+    /// Withdraw the fees accrued in one token (treasurer only)
+    /// 
+    /// Pays out at most the token's fee ledger, and never more than the pool
+    /// holds above the principal of held orders in that token. Anything that
+    /// cannot be paid yet stays on the ledger.
+    pub fn withdraw_fees(
+        env: Env,
+        caller: Address,
+        token: Address,
+        recipient: Address,
+    ) -> Result<i128, PoolError> {
+        access::require_role(&env, &caller, Role::Treasurer)?;
+        
+        let ledger = ledger::read_ledger(&env, &token);
+        let fees = ledger.fees_owed;
+        if fees <= 0 {
+            return Ok(0);
+        }
+        
+        // Escrowed principal and seller payables never leave through the fee path
+        let contract_addr = env.current_contract_address();
+        let token_client = token::Client::new(&env, &token);
+        let holdings = token_client.balance(&contract_addr) + Self::blend_position_value(&env, &token);
+        let amount = fees.min(holdings - ledger.escrowed - ledger.seller_payable);
+        if amount <= 0 {
+            return Err(PoolError::EscrowShortfall);
+        }
+        
+        if Self::ensure_liquidity(&env, &token, amount) < amount {
+            return Err(PoolError::InsufficientBalance);
+        }
+        
+        token_client.transfer(&contract_addr, &recipient, &amount);
+        ledger::record_fee_withdrawal(&env, &token, amount);
+        
+        log!(&env, "Fees withdrawn: {} of {} to {}", amount, token, recipient);
+        events::FeesWithdrawn { token, recipient, amount }.publish(&env);
+        
+        Ok(amount)
+    }
+
+    /// Fees accrued in a token and not yet withdrawn
+    pub fn get_fees_owed(env: Env, token: Address) -> i128 {
+        ledger::read_ledger(&env, &token).fees_owed
+    }
+
+    /// Withdraw the Blend interest earned on a token's float (treasurer only)
+    /// 
+    /// Pays out the surplus `reconcile` reports: wallet balance plus the Blend
+    /// position, less everything the pool owes in that token. This covers both
+    /// realized yield already back in the wallet and interest still supplied.
+    pub fn withdraw_yield(
+        env: Env,
+        caller: Address,
+        token: Address,
+        recipient: Address,
+    ) -> Result<i128, PoolError> {
+        access::require_role(&env, &caller, Role::Treasurer)?;
+        
+        let contract_addr = env.current_contract_address();
+        let token_client = token::Client::new(&env, &token);
+        let liabilities = Self::liabilities(&ledger::read_ledger(&env, &token));
+        let holdings = token_client.balance(&contract_addr) + Self::blend_position_value(&env, &token);
+        let amount = holdings - liabilities;
+        if amount <= 0 {
+            return Ok(0);
+        }
+        
+        if Self::ensure_liquidity(&env, &token, amount) < amount {
+            return Err(PoolError::InsufficientBalance);
+        }
+        
+        token_client.transfer(&contract_addr, &recipient, &amount);
+        
+        log!(&env, "Yield withdrawn: {} of {} to {}", amount, token, recipient);
+        events::YieldWithdrawn { token, recipient, amount }.publish(&env);
+        
+        Ok(amount)
+    }
+
+    /// Internal accounting for one token
+    pub fn get_ledger(env: Env, token: Address) -> TokenLedger {
+        ledger::read_ledger(&env, &token)
+    }
+
+    /// Queue a fee terms, seller or reserve Blend pool change (config admin only)
+    /// 
+    /// The change can be applied with `apply_change` once `CONFIG_CHANGE_DELAY`
+    /// has passed, and cancelled until then. Queuing replaces any pending
+    /// change of the same kind. Returns the time the change becomes effective.
+    pub fn queue_change(env: Env, caller: Address, change: ConfigChange) -> Result<u64, PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
+        
+        match &change {
+            ConfigChange::FeeTerms(fee_terms) => Self::check_fee_terms(&env, fee_terms)?,
+            ConfigChange::BlendPool(token, _, _) if reserves::read_reserve(&env, token).is_none() => {
+                return Err(PoolError::UnsupportedToken);
+            }
+            ConfigChange::BlendPool(token, blend_pool, reserve_index) => {
+                Self::check_blend_reserve(&env, token, blend_pool, *reserve_index)?;
+            }
+            _ => {}
+        }
+        
+        let kind = change.kind();
+        let effective_at = env.ledger().timestamp() + CONFIG_CHANGE_DELAY;
+        env.storage().instance().set(
+            &DataKey::QueuedChange(kind),
+            &QueuedChange { change: change.clone(), effective_at },
+        );
+        
+        log!(&env, "Config change queued, effective at {}", effective_at);
+        events::ChangeQueued { kind, change, effective_at }.publish(&env);
+        
+        Ok(effective_at)
+    }
+
+    /// Cancel a pending config change before it is applied (config admin only)
+    pub fn cancel_change(env: Env, caller: Address, kind: ChangeKind) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
+        
+        let key = DataKey::QueuedChange(kind);
+        if !env.storage().instance().has(&key) {
+            return Err(PoolError::NoQueuedChange);
+        }
+        env.storage().instance().remove(&key);
+        
+        events::ChangeCancelled { kind }.publish(&env);
+        Ok(())
+    }
+
+    /// Apply a queued config change once its notice period has passed (config admin only)
+    pub fn apply_change(env: Env, caller: Address, kind: ChangeKind) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
         
-        log!(&env, "Fees withdrawn: USDC={}, XLM={} to {}", fees_usdc, fees_xlm, recipient);
+        let key = DataKey::QueuedChange(kind);
+        let queued: QueuedChange = env.storage().instance().get(&key)
+            .ok_or(PoolError::NoQueuedChange)?;
+        if env.ledger().timestamp() < queued.effective_at {
+            return Err(PoolError::TimelockActive);
+        }
+        env.storage().instance().remove(&key);
         
-        (fees_usdc, fees_xlm)
+        match queued.change {
+            ConfigChange::FeeTerms(fee_terms) => {
+                // The cap may have been lowered since the change was queued
+                Self::check_fee_terms(&env, &fee_terms)?;
+                env.storage().instance().set(&DataKey::FeeTerms, &fee_terms);
+                log!(&env, "Fee terms updated to {}bp/{}bp",
+                     fee_terms.buyer_surcharge_bps, fee_terms.seller_commission_bps);
+                events::FeeTermsUpdated { fee_terms }.publish(&env);
+            }
+            ConfigChange::Seller(new_seller) => {
+                env.storage().instance().set(&DataKey::Seller, &new_seller);
+                log!(&env, "Seller updated to {}", new_seller);
+                events::SellerUpdated { seller: new_seller }.publish(&env);
+            }
+            ConfigChange::BlendPool(token, blend_pool, reserve_index) => {
+                let mut config = reserves::read_reserve(&env, &token)
+                    .ok_or(PoolError::UnsupportedToken)?;
+                // Shares in the old pool would be orphaned; withdraw them first
+                if ledger::read_ledger(&env, &token).b_tokens > 0 {
+                    return Err(PoolError::MigrationRequired);
+                }
+                Self::check_blend_reserve(&env, &token, &blend_pool, reserve_index)?;
+                config.blend_pool = Some(blend_pool);
+                config.reserve_index = reserve_index;
+                reserves::write_reserve(&env, &token, &config);
+                log!(&env, "Blend pool for {} updated", token);
+                events::ReserveUpdated { token, config }.publish(&env);
+            }
+        }
+        
+        Ok(())
     }
 
-    /// Update seller address (admin only)
-    pub fn set_seller(env: Env, new_seller: Address) {
-        Self::require_admin(&env);
-        env.storage().instance().set(&DataKey::Seller, &new_seller);
-        log!(&env, "Seller updated to {}", new_seller);
+    /// Pending config change of a kind, if any
+    pub fn get_queued_change(env: Env, kind: ChangeKind) -> Option<QueuedChange> {
+        env.storage().instance().get(&DataKey::QueuedChange(kind))
     }
 
-    /// Update fee percentage (admin only)
-    pub fn set_fee_percent(env: Env, new_fee_percent: u32) {
-        Self::require_admin(&env);
-        env.storage().instance().set(&DataKey::FeePercent, &new_fee_percent);
-        log!(&env, "Fee percent updated to {}bp", new_fee_percent);
+    /// Set the cap on each side of the fee terms (config admin only)
+    pub fn set_max_fee_bps(env: Env, caller: Address, max_fee_bps: u32) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
+        if max_fee_bps > 10000 {
+            return Err(PoolError::FeeTooHigh);
+        }
+        env.storage().instance().set(&DataKey::MaxFeeBps, &max_fee_bps);
+        log!(&env, "Max fee updated to {}bp", max_fee_bps);
+        events::MaxFeeUpdated { max_fee_bps }.publish(&env);
+        Ok(())
     }
 
-    /// Update Blend USDC token address (admin only)
-    /// This is the USDC variant that gets supplied to the Blend pool
-    pub fn set_blend_usdc_token(env: Env, new_blend_usdc_token: Address) {
-        Self::require_admin(&env);
-        env.storage().instance().set(&DataKey::BlendUsdcToken, &new_blend_usdc_token);
-        log!(&env, "Blend USDC token updated to {}", new_blend_usdc_token);
+    /// Cap on each side of the fee terms
+    pub fn get_max_fee_bps(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::MaxFeeBps).unwrap_or(DEFAULT_MAX_FEE_BPS)
     }
 
-    /// Claim BLND emissions from the Blend pool (admin only)
+    /// Fee terms applied to new deposits
+    pub fn get_fee_terms(env: Env) -> FeeTerms {
+        Self::fee_terms(&env)
+    }
+
+    /// Update the escrow period in seconds (config admin only)
+    /// Applies to deposits made after the change
+    pub fn set_escrow_period(env: Env, caller: Address, new_escrow_period: u64) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
+        if !(MIN_ESCROW_PERIOD..=MAX_ESCROW_PERIOD).contains(&new_escrow_period) {
+            return Err(PoolError::InvalidAmount);
+        }
+        env.storage().instance().set(&DataKey::EscrowPeriod, &new_escrow_period);
+        log!(&env, "Escrow period updated to {}s", new_escrow_period);
+        events::EscrowPeriodUpdated { escrow_period: new_escrow_period }.publish(&env);
+        Ok(())
+    }
+
+    /// List the tokens `deposit` currently accepts
+    pub fn get_accepted_tokens(env: Env) -> Vec<Address> {
+        let mut accepted: Vec<Address> = Vec::new(&env);
+        for (token_addr, config) in reserves::read_reserves(&env).iter() {
+            if config.accepted {
+                accepted.push_back(token_addr);
+            }
+        }
+        accepted
+    }
+
+    /// Claim BLND emissions from the Blend pool (settler only)
     /// 
     /// Calls the Blend pool's `claim` function to collect accrued BLND
     /// emissions for this contract's supply/collateral positions.
     /// 
     /// # Arguments
+    /// * `blend_pool` - Blend pool to claim from (must be a reserve's pool)
     /// * `reserve_token_ids` - Vec of emission indices to claim
     ///   (e.g., 6 = USDC supply emissions. Formula: reserve_index * 2 for supply)
-    pub fn claim_emissions(env: Env, reserve_token_ids: Vec<u32>) -> i128 {
-        Self::require_admin(&env);
+    pub fn claim_emissions(
+        env: Env,
+        caller: Address,
+        blend_pool: Address,
+        reserve_token_ids: Vec<u32>,
+    ) -> Result<i128, PoolError> {
+        access::require_role(&env, &caller, Role::Settler)?;
         
         let contract_addr = env.current_contract_address();
         
-        // Use the USDC Blend pool (same pool used for supply)
-        let blend_pool: Address = env.storage().instance()
-            .get(&DataKey::BlendPoolUsdc)
-            .expect("Blend pool not configured");
+        let configured = reserves::read_reserves(&env).values().iter()
+            .any(|config| config.blend_pool.as_ref() == Some(&blend_pool));
+        if !configured {
+            return Err(PoolError::BlendPoolNotConfigured);
+        }
+        let blnd_token: Address = env.storage().instance()
+            .get(&DataKey::BlndToken)
+            .ok_or(PoolError::BlndTokenNotConfigured)?;
         
         let blend_client = pool::Client::new(&env, &blend_pool);
         
@@ -540,37 +1271,445 @@ impl PoolContract {
             &contract_addr,
         );
         
+        // Emissions are platform revenue, withdrawn through the fee ledger
+        ledger::record_fee(&env, &blnd_token, claimed);
+        
         log!(&env, "Claimed {} BLND emissions from Blend pool", claimed);
+        events::EmissionsClaimed { pool: blend_pool, amount: claimed }.publish(&env);
+        
+        Ok(claimed)
+    }
+
+    /// Set the BLND token that claimed emissions are paid in (config admin only)
+    pub fn set_blnd_token(env: Env, caller: Address, blnd_token: Address) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::ConfigAdmin)?;
+        env.storage().instance().set(&DataKey::BlndToken, &blnd_token);
+        log!(&env, "BLND token updated to {}", blnd_token);
+        events::BlndTokenUpdated { token: blnd_token }.publish(&env);
+        Ok(())
+    }
+
+    /// Grant a role to an account (admin only)
+    pub fn grant_role(env: Env, role: Role, account: Address) -> Result<(), PoolError> {
+        Self::require_admin(&env)?;
+        access::grant(&env, role, &account);
+        Ok(())
+    }
+
+    /// Revoke a role from an account (admin only)
+    pub fn revoke_role(env: Env, role: Role, account: Address) -> Result<(), PoolError> {
+        Self::require_admin(&env)?;
+        access::revoke(&env, role, &account);
+        Ok(())
+    }
+
+    /// Check whether an account holds a role
+    pub fn has_role(env: Env, role: Role, account: Address) -> bool {
+        access::has_role(&env, role, &account)
+    }
+
+    /// Address that manages roles
+    pub fn get_admin(env: Env) -> Result<Address, PoolError> {
+        env.storage().instance().get(&DataKey::Admin).ok_or(PoolError::NotInitialized)
+    }
+
+    /// Propose a new admin (admin only)
+    /// The new admin must call `accept_admin` within `ADMIN_TRANSFER_WINDOW`
+    pub fn propose_admin(env: Env, new_admin: Address) -> Result<u64, PoolError> {
+        Self::require_admin(&env)?;
+        
+        let deadline = env.ledger().timestamp() + ADMIN_TRANSFER_WINDOW;
+        env.storage().instance().set(
+            &DataKey::PendingAdmin,
+            &PendingAdmin { new_admin: new_admin.clone(), deadline },
+        );
+        
+        events::AdminProposed { new_admin, deadline }.publish(&env);
+        Ok(deadline)
+    }
+
+    /// Accept a pending admin transfer (proposed admin only)
+    /// Every role the previous admin held moves to the new admin
+    pub fn accept_admin(env: Env) -> Result<(), PoolError> {
+        let pending: PendingAdmin = env.storage().instance().get(&DataKey::PendingAdmin)
+            .ok_or(PoolError::NoPendingTransfer)?;
+        pending.new_admin.require_auth();
+        if env.ledger().timestamp() > pending.deadline {
+            return Err(PoolError::TransferExpired);
+        }
+        
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        Self::set_admin(&env, pending.new_admin);
+        Ok(())
+    }
+
+    /// Withdraw a pending admin transfer (admin only)
+    pub fn cancel_admin_transfer(env: Env) -> Result<(), PoolError> {
+        Self::require_admin(&env)?;
+        if !env.storage().instance().has(&DataKey::PendingAdmin) {
+            return Err(PoolError::NoPendingTransfer);
+        }
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        events::AdminTransferCancelled {}.publish(&env);
+        Ok(())
+    }
+
+    /// Pending admin transfer, if any
+    pub fn get_pending_admin(env: Env) -> Option<PendingAdmin> {
+        env.storage().instance().get(&DataKey::PendingAdmin)
+    }
+
+    /// Set or clear the guardian that can recover the admin role (admin only)
+    pub fn set_guardian(env: Env, guardian: Option<Address>) -> Result<(), PoolError> {
+        Self::require_admin(&env)?;
+        match &guardian {
+            Some(addr) => env.storage().instance().set(&DataKey::Guardian, addr),
+            None => {
+                env.storage().instance().remove(&DataKey::Guardian);
+                env.storage().instance().remove(&DataKey::PendingRecovery);
+            }
+        }
+        events::GuardianUpdated { guardian }.publish(&env);
+        Ok(())
+    }
+
+    /// Current guardian, if any
+    pub fn get_guardian(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::Guardian)
+    }
+
+    /// Start rotating the admin to `new_admin` (guardian only)
+    /// Completes after `GUARDIAN_RECOVERY_DELAY` unless the admin cancels it
+    pub fn initiate_recovery(env: Env, new_admin: Address) -> Result<u64, PoolError> {
+        let guardian = Self::require_guardian(&env)?;
+        
+        let deadline = env.ledger().timestamp() + GUARDIAN_RECOVERY_DELAY;
+        env.storage().instance().set(
+            &DataKey::PendingRecovery,
+            &PendingAdmin { new_admin: new_admin.clone(), deadline },
+        );
         
-        claimed
+        events::RecoveryInitiated { guardian, new_admin, deadline }.publish(&env);
+        Ok(deadline)
     }
 
-    /// Upgrade contract WASM (admin only)
-    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) {
-        Self::require_admin(&env);
+    /// Finish a guardian rotation once its delay has passed (guardian only)
+    pub fn complete_recovery(env: Env) -> Result<(), PoolError> {
+        Self::require_guardian(&env)?;
+        
+        let pending: PendingAdmin = env.storage().instance().get(&DataKey::PendingRecovery)
+            .ok_or(PoolError::NoPendingTransfer)?;
+        if env.ledger().timestamp() < pending.deadline {
+            return Err(PoolError::TimelockActive);
+        }
+        
+        env.storage().instance().remove(&DataKey::PendingRecovery);
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        Self::set_admin(&env, pending.new_admin);
+        Ok(())
+    }
+
+    /// Stop a guardian rotation before it completes (admin only)
+    pub fn cancel_recovery(env: Env) -> Result<(), PoolError> {
+        Self::require_admin(&env)?;
+        if !env.storage().instance().has(&DataKey::PendingRecovery) {
+            return Err(PoolError::NoPendingTransfer);
+        }
+        env.storage().instance().remove(&DataKey::PendingRecovery);
+        events::RecoveryCancelled {}.publish(&env);
+        Ok(())
+    }
+
+    /// Pending guardian rotation, if any
+    pub fn get_pending_recovery(env: Env) -> Option<PendingAdmin> {
+        env.storage().instance().get(&DataKey::PendingRecovery)
+    }
+
+    /// Upgrade contract WASM (upgrader only)
+    pub fn upgrade(env: Env, caller: Address, new_wasm_hash: BytesN<32>) -> Result<(), PoolError> {
+        access::require_role(&env, &caller, Role::Upgrader)?;
+        events::Upgraded { new_wasm_hash: new_wasm_hash.clone() }.publish(&env);
         env.deployer().update_current_contract_wasm(new_wasm_hash);
+        Ok(())
     }
 
     // ============================================================
     // INTERNAL HELPERS
     // ============================================================
 
-    fn require_admin(env: &Env) {
+    fn require_admin(env: &Env) -> Result<(), PoolError> {
         let admin: Address = env.storage().instance().get(&DataKey::Admin)
-            .expect("Not initialized");
+            .ok_or(PoolError::NotInitialized)?;
         admin.require_auth();
+        Ok(())
+    }
+
+    fn require_not_paused(env: &Env) -> Result<(), PoolError> {
+        if env.storage().instance().get(&DataKey::Paused).unwrap_or(false) {
+            return Err(PoolError::ContractPaused);
+        }
+        Ok(())
+    }
+
+    fn require_guardian(env: &Env) -> Result<Address, PoolError> {
+        let guardian: Address = env.storage().instance().get(&DataKey::Guardian)
+            .ok_or(PoolError::NoGuardian)?;
+        guardian.require_auth();
+        Ok(guardian)
+    }
+
+    fn set_admin(env: &Env, new_admin: Address) {
+        let previous: Address = env.storage().instance().get(&DataKey::Admin)
+            .expect("admin is set once initialized");
+        env.storage().instance().set(&DataKey::Admin, &new_admin);
+        access::transfer_roles(env, &previous, &new_admin);
+        log!(env, "Admin transferred from {} to {}", previous, new_admin);
+        events::AdminTransferred { previous, new_admin }.publish(env);
+    }
+
+    /// Load a deposit that is still held in escrow
+    fn load_held_deposit(env: &Env, order_id: u64) -> Result<Deposit, PoolError> {
+        let deposit = storage::read_deposit(env, order_id).ok_or(PoolError::OrderNotFound)?;
+        
+        if deposit.status != DepositStatus::Held {
+            return Err(PoolError::OrderNotHeld);
+        }
+        
+        Ok(deposit)
+    }
+
+    /// Move a held deposit to its final status, persist it and take it out of the escrow totals
+    /// Returns the fee retained by the pool (only charged when released to the seller)
+    fn close_deposit(env: &Env, deposit: &mut Deposit, status: DepositStatus) -> i128 {
+        deposit.status = status;
+        storage::write_deposit(env, deposit);
+        
+        let (_, fee) = deposit.fee_terms.split(deposit.amount);
+        if status != DepositStatus::Released {
+            ledger::record_refund(env, &deposit.token, deposit.amount, fee);
+            return 0;
+        }
+        
+        ledger::record_release(env, &deposit.token, deposit.amount, fee);
+        
+        fee
+    }
+
+    /// Reject fee terms above the configured cap
+    fn check_fee_terms(env: &Env, fee_terms: &FeeTerms) -> Result<(), PoolError> {
+        let max_fee_bps: u32 = env.storage().instance()
+            .get(&DataKey::MaxFeeBps).unwrap_or(DEFAULT_MAX_FEE_BPS);
+        if fee_terms.buyer_surcharge_bps > max_fee_bps || fee_terms.seller_commission_bps > max_fee_bps {
+            return Err(PoolError::FeeTooHigh);
+        }
+        Ok(())
+    }
+
+    /// Fee terms currently in force for new deposits
+    fn fee_terms(env: &Env) -> FeeTerms {
+        env.storage().instance().get(&DataKey::FeeTerms)
+            .unwrap_or(FeeTerms { buyer_surcharge_bps: 100, seller_commission_bps: 100 })
+    }
+
+    /// Make sure the contract wallet holds at least `amount` of `token`,
+    /// withdrawing the shortfall from Blend if the token is supplied there.
+    /// The withdrawal is capped by our position and the reserve's free liquidity,
+    /// so the returned wallet balance can still be below `amount`.
+    fn ensure_liquidity(env: &Env, token: &Address, amount: i128) -> i128 {
+        Self::try_ensure_liquidity(env, token, amount).unwrap_or_else(|error| panic_with_error!(env, error))
+    }
+
+    /// `ensure_liquidity`, returning `BlendOperationFailed` instead of reverting
+    /// if Blend rejects the withdrawal
+    fn try_ensure_liquidity(env: &Env, token: &Address, amount: i128) -> Result<i128, PoolError> {
+        let contract_addr = env.current_contract_address();
+        let token_client = token::Client::new(env, token);
+        let balance = token_client.balance(&contract_addr);
+        if balance >= amount {
+            return Ok(balance);
+        }
+        
+        let Some(config) = reserves::read_reserve(env, token) else {
+            return Ok(balance);
+        };
+        let Some(pool_addr) = config.blend_pool else {
+            return Ok(balance);
+        };
+        let blend_client = pool::Client::new(env, &pool_addr);
+        
+        let reserve = match blend_client.try_get_reserve(token) {
+            Ok(Ok(reserve)) => reserve,
+            _ => return Err(PoolError::BlendOperationFailed),
+        };
+        let position_value = Self::blend_position_value(env, token);
+        
+        // Blend rejects withdrawals that push utilization above the reserve's max_util,
+        // so only supply in excess of borrowed / max_util is free
+        let reserve_supplied = reserve.data.b_supply * reserve.data.b_rate / SCALAR_12;
+        let reserve_borrowed = reserve.data.d_supply * reserve.data.d_rate / SCALAR_12;
+        let max_util = reserve.config.max_util as i128;
+        let min_supply = (reserve_borrowed * SCALAR_7 + max_util - 1) / max_util;
+        let free_liquidity = reserve_supplied - min_supply;
+        
+        let withdraw_amount = (amount - balance).min(position_value).min(free_liquidity);
+        if withdraw_amount <= 0 {
+            log!(env, "No Blend liquidity to withdraw for {}", token);
+            return Ok(balance);
+        }
+        
+        Self::withdraw_position(env, token, &pool_addr, config.supply_mode, reserve.config.index, withdraw_amount)
     }
 
-    /// Check if a token address matches either Circle USDC or Blend USDC
-    fn is_usdc_token(env: &Env, token: &Address) -> bool {
-        if let Some(circle) = env.storage().instance().get::<_, Address>(&DataKey::UsdcToken) {
-            if *token == circle { return true; }
+    /// Withdraw `amount` of `token` from our `mode` position in `pool_addr`,
+    /// returning the new wallet balance
+    /// Blend caps the withdrawal at the position, burning all of its b-tokens.
+    /// Nothing moves if Blend rejects the withdrawal.
+    fn withdraw_position(
+        env: &Env,
+        token: &Address,
+        pool_addr: &Address,
+        mode: SupplyMode,
+        reserve_index: u32,
+        amount: i128,
+    ) -> Result<i128, PoolError> {
+        let contract_addr = env.current_contract_address();
+        let token_client = token::Client::new(env, token);
+        let balance = token_client.balance(&contract_addr);
+        log!(env, "Withdrawing {} of {} from Blend pool {}", amount, token, pool_addr);
+        
+        let mut requests: Vec<pool::Request> = Vec::new(env);
+        requests.push_back(pool::Request {
+            request_type: mode.withdraw_request(),
+            address: token.clone(),
+            amount,
+        });
+        let positions = match pool::Client::new(env, pool_addr).try_submit(
+            &contract_addr,
+            &contract_addr,
+            &contract_addr,
+            &requests,
+        ) {
+            Ok(Ok(positions)) => positions,
+            _ => {
+                log!(env, "Blend pool {} rejected the withdrawal", pool_addr);
+                return Err(PoolError::BlendOperationFailed);
+            }
+        };
+        let new_balance = token_client.balance(&contract_addr);
+        ledger::record_withdraw(
+            env,
+            token,
+            new_balance - balance,
+            mode.b_tokens(&positions, reserve_index),
+        );
+        events::WithdrawnFromBlend {
+            token: token.clone(),
+            pool: pool_addr.clone(),
+            amount: new_balance - balance,
+        }.publish(env);
+        
+        Ok(new_balance)
+    }
+
+    /// Current underlying value of our Blend position in `token` (0 if it is not supplied)
+    fn blend_position_value(env: &Env, token: &Address) -> i128 {
+        let Some(config) = reserves::read_reserve(env, token) else {
+            return 0;
+        };
+        let Some(pool_addr) = config.blend_pool else {
+            return 0;
+        };
+        let blend_client = pool::Client::new(env, &pool_addr);
+        
+        // Views must keep working if the configured pool is unreachable
+        let reserve = match blend_client.try_get_reserve(token) {
+            Ok(Ok(reserve)) => reserve,
+            _ => return 0,
+        };
+        let positions = match blend_client.try_get_positions(&env.current_contract_address()) {
+            Ok(Ok(positions)) => positions,
+            _ => return 0,
+        };
+        let b_tokens = config.supply_mode.b_tokens(&positions, reserve.config.index);
+        b_tokens * reserve.data.b_rate / SCALAR_12
+    }
+
+    /// Check that `blend_pool` lists `token` as its reserve at `reserve_index`
+    fn check_blend_reserve(
+        env: &Env,
+        token: &Address,
+        blend_pool: &Address,
+        reserve_index: u32,
+    ) -> Result<pool::Reserve, PoolError> {
+        match pool::Client::new(env, blend_pool).try_get_reserve(token) {
+            Ok(Ok(reserve)) if reserve.asset == *token && reserve.config.index == reserve_index => Ok(reserve),
+            _ => Err(PoolError::NotBlendReserve),
         }
-        if let Some(blend) = env.storage().instance().get::<_, Address>(&DataKey::BlendUsdcToken) {
-            if *token == blend { return true; }
+    }
+
+    /// Check that a reserve's Blend pool can safely take new supply of `token`
+    /// The pool must be active (not on ice or frozen) and list the token at the
+    /// configured index as an enabled reserve whose utilisation is no higher
+    /// than the reserve's `max_util_bps`.
+    fn supply_health(
+        env: &Env,
+        token: &Address,
+        pool_addr: &Address,
+        config: &ReserveConfig,
+    ) -> Result<(), PoolError> {
+        // 0 = admin active, 1 = active; on ice, frozen and setup are all higher
+        match pool::Client::new(env, pool_addr).try_get_config() {
+            Ok(Ok(pool_config)) if pool_config.status <= 1 => {}
+            _ => return Err(PoolError::BlendPoolUnhealthy),
+        }
+        
+        let reserve = Self::check_blend_reserve(env, token, pool_addr, config.reserve_index)?;
+        if !reserve.config.enabled {
+            return Err(PoolError::BlendPoolUnhealthy);
+        }
+        
+        let supplied = reserve.data.b_supply * reserve.data.b_rate / SCALAR_12;
+        let borrowed = reserve.data.d_supply * reserve.data.d_rate / SCALAR_12;
+        if supplied > 0 && borrowed * 10000 > supplied * config.max_util_bps as i128 {
+            return Err(PoolError::UtilizationTooHigh);
         }
-        false
+        Ok(())
+    }
+
+    /// Blend pool holding a token's position, if any (wallet-only tokens are never supplied)
+    fn blend_pool_for(env: &Env, token: &Address) -> Option<Address> {
+        reserves::read_reserve(env, token).and_then(|config| config.blend_pool)
+    }
+
+    /// Check if a token is accepted for deposits
+    fn is_accepted_token(env: &Env, token: &Address) -> bool {
+        reserves::read_reserve(env, token).is_some_and(|config| config.accepted)
+    }
+
+    /// Funds the pool owes out of a token's holdings
+    fn liabilities(ledger: &TokenLedger) -> i128 {
+        ledger.escrowed + ledger.seller_payable + ledger.fees_owed
+    }
+
+    /// Wallet balance a reserve keeps liquid for what the pool owes in it
+    fn buffer_target(env: &Env, token: &Address, config: &ReserveConfig) -> i128 {
+        config.buffer_target(Self::liabilities(&ledger::read_ledger(env, token)))
+    }
+
+    /// Whether the pool has never recorded or held any of a token
+    fn is_untouched(env: &Env, token: &Address) -> bool {
+        !ledger::has_ledger(env, token) && Self::wallet_balance(env, token) == 0
+    }
+
+    /// Tokens held by the contract itself (outside Blend)
+    fn wallet_balance(env: &Env, token: &Address) -> i128 {
+        token::Client::new(env, token).balance(&env.current_contract_address())
     }
 }
 
+mod access;
+mod events;
+mod ledger;
+mod reserves;
+mod storage;
 mod test;
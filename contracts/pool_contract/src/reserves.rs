@@ -0,0 +1,164 @@
+// Per-token reserve configuration for PoolContract
+//
+// Every token the pool handles has one `ReserveConfig`, keyed by token address
+// in a single map. It says whether `deposit` accepts the token and whether, and
+// where, its idle balance is supplied to Blend. Adding an asset such as EURC is
+// a config change rather than new storage keys and another copy of the supply
+// code.
+//
+// A reserve can keep a buffer of its liabilities liquid in the wallet so that
+// refunds and small settlements are paid without a Blend withdrawal; only the
+// balance above the buffer is supplied.
+//
+// Supply goes into either a plain Supply or a SupplyCollateral position,
+// chosen per reserve. The escrow float never borrows, so plain Supply is the
+// default and keeps it out of the pool's collateral accounting.
+//
+// Before supplying, the pool checks that the Blend pool is active, lists the
+// token at the configured index and is below the reserve's utilisation
+// ceiling. A supply skipped by those checks, or one Blend rejects (e.g. over
+// its supply cap), leaves the funds in the wallet and records a
+// `PendingSupply` so the keeper can retry it later.
+
+use blend_contract_sdk::pool;
+use soroban_sdk::{contracttype, Address, Env, Map};
+
+use crate::{DataKey, PoolError};
+
+/// Blend position a reserve's supply is held in
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SupplyMode {
+    Supply,     // plain supply: earns interest, not counted as collateral
+    Collateral, // supply collateral: earns interest and could back borrowing
+}
+
+impl SupplyMode {
+    /// Blend request type that adds to this position
+    pub fn supply_request(self) -> u32 {
+        match self {
+            SupplyMode::Supply => 0,     // Supply
+            SupplyMode::Collateral => 2, // SupplyCollateral
+        }
+    }
+
+    /// Blend request type that draws down this position
+    pub fn withdraw_request(self) -> u32 {
+        match self {
+            SupplyMode::Supply => 1,     // Withdraw
+            SupplyMode::Collateral => 3, // WithdrawCollateral
+        }
+    }
+
+    /// b-tokens held in this position for the reserve at `index`
+    pub fn b_tokens(self, positions: &pool::Positions, index: u32) -> i128 {
+        let held = match self {
+            SupplyMode::Supply => &positions.supply,
+            SupplyMode::Collateral => &positions.collateral,
+        };
+        held.get(index).unwrap_or(0)
+    }
+}
+
+/// How the pool handles one token
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReserveConfig {
+    pub accepted: bool,              // `deposit` accepts the token
+    pub blend_pool: Option<Address>, // Blend pool the token is supplied to (None = wallet only)
+    pub reserve_index: u32,          // index of the token's reserve in `blend_pool`
+    pub supply_threshold: i128,      // smallest idle balance worth a Blend submit
+    pub buffer_bps: u32,             // share of the token's liabilities kept liquid in the wallet
+    pub supply_mode: SupplyMode,     // Blend position new supply goes into
+    pub max_util_bps: u32,           // no new supply while the Blend reserve's utilisation is above this
+    pub decimals: u32,               // token decimals, for clients formatting amounts
+    pub enabled: bool,               // new supply to `blend_pool` is switched on
+}
+
+impl ReserveConfig {
+    /// Accepted for payment and kept in the contract wallet
+    pub fn wallet_only(decimals: u32) -> Self {
+        ReserveConfig {
+            accepted: true,
+            blend_pool: None,
+            reserve_index: 0,
+            supply_threshold: 0,
+            buffer_bps: 0,
+            supply_mode: SupplyMode::Supply,
+            max_util_bps: 9000,
+            decimals,
+            enabled: false,
+        }
+    }
+
+    /// Wallet balance to keep for `liabilities` before supplying the rest
+    pub fn buffer_target(&self, liabilities: i128) -> i128 {
+        liabilities.max(0) * self.buffer_bps as i128 / 10000
+    }
+
+    /// Pool that new supply goes to, if supply is switched on
+    ///
+    /// Existing positions are still withdrawn from `blend_pool` when supply is
+    /// switched off.
+    pub fn supply_pool(&self) -> Option<Address> {
+        if self.enabled {
+            self.blend_pool.clone()
+        } else {
+            None
+        }
+    }
+}
+
+/// Idle balance whose Blend supply failed and is waiting for a retry
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingSupply {
+    pub amount: i128,     // excess left in the wallet by the last failed submit
+    pub error: u32,       // `PoolError` code of the last failure
+    pub failed_at: u64,   // ledger timestamp of the last failure
+    pub attempts: u32,    // failed submits since the last successful supply
+}
+
+pub fn read_reserves(env: &Env) -> Map<Address, ReserveConfig> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Reserves)
+        .unwrap_or(Map::new(env))
+}
+
+pub fn read_reserve(env: &Env, token: &Address) -> Option<ReserveConfig> {
+    read_reserves(env).get(token.clone())
+}
+
+pub fn write_reserve(env: &Env, token: &Address, config: &ReserveConfig) {
+    let mut reserves = read_reserves(env);
+    reserves.set(token.clone(), config.clone());
+    env.storage().instance().set(&DataKey::Reserves, &reserves);
+}
+
+pub fn read_pending_supply(env: &Env, token: &Address) -> Option<PendingSupply> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PendingSupply(token.clone()))
+}
+
+/// Mark `amount` of `token` as idle after a Blend supply failed or was skipped
+pub fn record_supply_failure(env: &Env, token: &Address, amount: i128, error: PoolError) -> PendingSupply {
+    let attempts = read_pending_supply(env, token).map_or(0, |p| p.attempts);
+    let pending = PendingSupply {
+        amount,
+        error: error as u32,
+        failed_at: env.ledger().timestamp(),
+        attempts: attempts + 1,
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::PendingSupply(token.clone()), &pending);
+    pending
+}
+
+pub fn clear_pending_supply(env: &Env, token: &Address) {
+    env.storage()
+        .instance()
+        .remove(&DataKey::PendingSupply(token.clone()));
+}
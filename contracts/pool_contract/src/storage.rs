@@ -0,0 +1,108 @@
+// Persistent storage for per-order deposit records
+//
+// Each deposit lives under its own `DataKey::Deposit(order_id)` entry so the
+// instance entry stays small no matter how many orders the store processes.
+// Sequential indexes (`DataKey::DepositIndex(n)` overall and
+// `DataKey::BuyerDeposit(buyer, n)` per buyer) support paginated reporting.
+// Settlement walks the same overall index from `DataKey::SettleCursor`, the
+// first position no batch has looked at yet, so no entry has to list every
+// held order. Orders a batch could not pay stay Held for `release`.
+
+use soroban_sdk::{Address, Env};
+
+use crate::{DataKey, Deposit};
+
+/// ~5s ledgers
+const DAY_IN_LEDGERS: u32 = 17280;
+
+/// Deposit entries are kept alive well past the default escrow period
+const DEPOSIT_BUMP_AMOUNT: u32 = 90 * DAY_IN_LEDGERS;
+const DEPOSIT_LIFETIME_THRESHOLD: u32 = DEPOSIT_BUMP_AMOUNT - 7 * DAY_IN_LEDGERS;
+
+fn extend(env: &Env, key: &DataKey) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, DEPOSIT_LIFETIME_THRESHOLD, DEPOSIT_BUMP_AMOUNT);
+}
+
+pub fn has_deposit(env: &Env, order_id: u64) -> bool {
+    env.storage().persistent().has(&DataKey::Deposit(order_id))
+}
+
+pub fn read_deposit(env: &Env, order_id: u64) -> Option<Deposit> {
+    let key = DataKey::Deposit(order_id);
+    let deposit: Option<Deposit> = env.storage().persistent().get(&key);
+    if deposit.is_some() {
+        extend(env, &key);
+    }
+    deposit
+}
+
+pub fn write_deposit(env: &Env, deposit: &Deposit) {
+    let key = DataKey::Deposit(deposit.order_id);
+    env.storage().persistent().set(&key, deposit);
+    extend(env, &key);
+}
+
+/// Record a new order in the sequential index
+pub fn index_deposit(env: &Env, order_id: u64) {
+    let count = deposit_count(env);
+    let key = DataKey::DepositIndex(count);
+    env.storage().persistent().set(&key, &order_id);
+    extend(env, &key);
+    env.storage().instance().set(&DataKey::DepositCount, &(count + 1));
+}
+
+/// Number of deposits ever recorded
+pub fn deposit_count(env: &Env) -> u64 {
+    env.storage().instance().get(&DataKey::DepositCount).unwrap_or(0)
+}
+
+/// Order id of the `index`-th deposit, in deposit order
+pub fn deposit_id_at(env: &Env, index: u64) -> Option<u64> {
+    let key = DataKey::DepositIndex(index);
+    let order_id: Option<u64> = env.storage().persistent().get(&key);
+    if order_id.is_some() {
+        extend(env, &key);
+    }
+    order_id
+}
+
+/// Record a new order in its buyer's index
+pub fn index_buyer_deposit(env: &Env, buyer: &Address, order_id: u64) {
+    let count = buyer_deposit_count(env, buyer);
+    let key = DataKey::BuyerDeposit(buyer.clone(), count);
+    env.storage().persistent().set(&key, &order_id);
+    extend(env, &key);
+
+    let count_key = DataKey::BuyerDepositCount(buyer.clone());
+    env.storage().persistent().set(&count_key, &(count + 1));
+    extend(env, &count_key);
+}
+
+/// Number of deposits ever made by `buyer`
+pub fn buyer_deposit_count(env: &Env, buyer: &Address) -> u64 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::BuyerDepositCount(buyer.clone()))
+        .unwrap_or(0)
+}
+
+/// Order id of `buyer`'s `index`-th deposit, in deposit order
+pub fn buyer_deposit_id_at(env: &Env, buyer: &Address, index: u64) -> Option<u64> {
+    let key = DataKey::BuyerDeposit(buyer.clone(), index);
+    let order_id: Option<u64> = env.storage().persistent().get(&key);
+    if order_id.is_some() {
+        extend(env, &key);
+    }
+    order_id
+}
+
+/// Index position the next settlement batch starts from
+pub fn settle_cursor(env: &Env) -> u64 {
+    env.storage().instance().get(&DataKey::SettleCursor).unwrap_or(0)
+}
+
+pub fn write_settle_cursor(env: &Env, cursor: u64) {
+    env.storage().instance().set(&DataKey::SettleCursor, &cursor);
+}
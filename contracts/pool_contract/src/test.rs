@@ -2,55 +2,224 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{testutils::Address as _, Address, Env};
+use blend_contract_sdk::testutils::{default_reserve_config, BlendFixture};
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{
+        storage::Persistent as _, Address as _, BytesN as _, Events as _, IssuerFlags, Ledger,
+        MockAuth, MockAuthInvoke,
+    },
+    token::{StellarAssetClient, TokenClient},
+    xdr::{ScErrorCode, ScErrorType},
+    Address, BytesN, Env, Event, Map, String, Symbol, Val, Vec,
+};
+
+/// Minimal SEP-40 oracle pricing every asset at $1, so Blend can value positions
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn decimals(_env: Env) -> u32 {
+        7
+    }
+
+    pub fn lastprice(env: Env, _asset: pool::Asset) -> Option<pool::PriceData> {
+        Some(pool::PriceData {
+            price: 1_0000000,
+            timestamp: env.ledger().timestamp(),
+        })
+    }
+}
+
+/// Deploy the Blend protocol with an active pool that lists `reserves`
+fn create_blend_pool(env: &Env, reserves: &[&Address]) -> Address {
+    deploy_blend_pool(env, reserves.iter().map(|token| (*token, default_reserve_config())))
+}
+
+/// Deploy the Blend protocol with an active pool listing each token with its own config
+fn deploy_blend_pool<'a>(env: &Env, reserves: impl IntoIterator<Item = (&'a Address, pool::ReserveConfig)>) -> Address {
+    let deployer = Address::generate(env);
+    let blnd = env.register_stellar_asset_contract_v2(deployer.clone()).address();
+    let backstop_usdc = env.register_stellar_asset_contract_v2(deployer.clone()).address();
+    let blend = BlendFixture::deploy(env, &deployer, &blnd, &backstop_usdc);
+    
+    let oracle = env.register(MockOracle, ());
+    let pool_addr = blend.pool_factory.deploy(
+        &deployer,
+        &String::from_str(env, "escrow"),
+        &BytesN::<32>::random(env),
+        &oracle,
+        &1000000,   // 10% backstop take rate
+        &4,         // max positions
+        &1_0000000, // min collateral
+    );
+    let pool_client = pool::Client::new(env, &pool_addr);
+    for (reserve, config) in reserves {
+        pool_client.queue_set_reserve(reserve, &config);
+        pool_client.set_reserve(reserve);
+    }
+    
+    blend.backstop.deposit(&deployer, &pool_addr, &50000_0000000);
+    pool_client.set_status(&3);
+    pool_client.update_status();
+    
+    pool_addr
+}
+
+/// Register a Stellar Asset Contract and mint `amount` to `to`
+fn create_token(env: &Env, to: &Address, amount: i128) -> Address {
+    let issuer = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(issuer).address();
+    StellarAssetClient::new(env, &token).mint(to, &amount);
+    token
+}
+
+/// Deploy a pool with a buyer funded in USDC and XLM
+/// Returns (client, usdc_token, xlm_token, buyer, seller)
+fn setup_escrow(env: &Env) -> (PoolContractClient<'_>, Address, Address, Address, Address) {
+    env.mock_all_auths();
+    
+    let admin = Address::generate(env);
+    let seller = Address::generate(env);
+    let buyer = Address::generate(env);
+    let usdc_token = create_token(env, &buyer, 1000_0000000);
+    let xlm_token = create_token(env, &buyer, 1000_0000000);
+    
+    let contract_id = env.register(
+        PoolContract,
+        (&admin, &seller, 0u32, 200u32, soroban_sdk::vec![env, usdc_token.clone(), xlm_token.clone()]),
+    );
+    let client = PoolContractClient::new(env, &contract_id);
+    
+    (client, usdc_token, xlm_token, buyer, seller)
+}
+
+/// Supply `token` to `blend_pool` from now on, adding it to the accepted tokens
+fn add_blend_reserve(env: &Env, client: &PoolContractClient, token: &Address, blend_pool: &Address) {
+    let admin = client.get_admin();
+    let reserve_index = pool::Client::new(env, blend_pool).get_reserve(token).config.index;
+    client.set_reserve(&admin, token, &ReserveConfig {
+        accepted: true,
+        blend_pool: Some(blend_pool.clone()),
+        reserve_index,
+        supply_threshold: 0,
+        buffer_bps: 0,
+        supply_mode: SupplyMode::Supply,
+        max_util_bps: 9000,
+        decimals: 7,
+        enabled: true,
+    });
+}
+
+/// Queue a config change and apply it once the notice period has passed
+fn apply_change_now(env: &Env, client: &PoolContractClient, change: &ConfigChange) {
+    let admin = client.get_admin();
+    client.queue_change(&admin, change);
+    env.ledger().with_mut(|li| li.timestamp += CONFIG_CHANGE_DELAY);
+    client.apply_change(&admin, &change.kind());
+}
 
 #[test]
 fn test_initialize() {
     let env = Env::default();
-    let contract_id = env.register(PoolContract, ());
-    let client = PoolContractClient::new(&env, &contract_id);
     
     let admin = Address::generate(&env);
     let seller = Address::generate(&env);
-    let usdc_token = Address::generate(&env);
+    let usdc_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let xlm_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
     
-    client.initialize(&admin, &seller, &200u32, &usdc_token);
+    let contract_id = env.register(
+        PoolContract,
+        (&admin, &seller, 0u32, 200u32, soroban_sdk::vec![&env, usdc_token.clone(), xlm_token.clone()]),
+    );
+    let client = PoolContractClient::new(&env, &contract_id);
     
-    let (total_usdc, total_xlm, fees_usdc, fees_xlm, supplied) = client.get_status();
+    assert_eq!(client.get_admin(), admin);
+    assert_eq!(client.get_fee_terms(), FeeTerms { buyer_surcharge_bps: 0, seller_commission_bps: 200 });
     
-    assert_eq!(total_usdc, 0);
-    assert_eq!(total_xlm, 0);
-    assert_eq!(fees_usdc, 0);
-    assert_eq!(fees_xlm, 0);
-    assert_eq!(supplied, false);
+    assert_eq!(client.get_accepted_tokens().len(), 2);
+    assert_eq!(client.get_reserve(&usdc_token), Some(ReserveConfig::wallet_only(7)));
+    
+    let status = client.get_status(&usdc_token);
+    assert_eq!(status.total_deposits, 0);
+    assert_eq!(status.fees_earned, 0);
+    assert!(!status.supplied_to_blend);
+    assert_eq!(client.get_status(&xlm_token).total_deposits, 0);
 }
 
 #[test]
-fn test_set_blend_usdc_token() {
+fn test_constructor_cannot_be_called_again() {
     let env = Env::default();
-    env.mock_all_auths();
+    let (client, usdc_token, xlm_token, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let attacker = Address::generate(&env);
     
-    let contract_id = env.register(PoolContract, ());
-    let client = PoolContractClient::new(&env, &contract_id);
+    // The host only runs `__constructor` at deployment; later calls are rejected
+    let tokens = soroban_sdk::vec![&env, usdc_token, xlm_token];
+    let args: Vec<Val> = (&attacker, &attacker, 0u32, 0u32, tokens).into_val(&env);
+    let result = env.try_invoke_contract::<(), soroban_sdk::Error>(
+        &client.address,
+        &Symbol::new(&env, "__constructor"),
+        args,
+    );
+    assert_eq!(
+        result,
+        Err(Ok(soroban_sdk::Error::from_type_and_code(ScErrorType::Context, ScErrorCode::InvalidAction)))
+    );
+    assert_eq!(client.get_admin(), admin);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #15)")]
+fn test_constructor_rejects_fees_above_cap() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let usdc_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    
+    // FeeTooHigh fails the deployment itself
+    env.register(
+        PoolContract,
+        (&admin, &seller, 0u32, DEFAULT_MAX_FEE_BPS + 1, soroban_sdk::vec![&env, usdc_token]),
+    );
+}
+
+#[test]
+fn test_set_reserve_adds_token() {
+    let env = Env::default();
+    env.mock_all_auths();
     
     let admin = Address::generate(&env);
     let seller = Address::generate(&env);
-    let circle_usdc = Address::generate(&env);
-    let blend_usdc = Address::generate(&env);
+    let circle_usdc = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let blend_usdc = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let xlm_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    
+    // Deploy with Circle USDC
+    let contract_id = env.register(
+        PoolContract,
+        (&admin, &seller, 0u32, 200u32, soroban_sdk::vec![&env, circle_usdc.clone(), xlm_token.clone()]),
+    );
+    let client = PoolContractClient::new(&env, &contract_id);
     
-    // Initialize with Circle USDC
-    client.initialize(&admin, &seller, &200u32, &circle_usdc);
+    // Any SAC can be added without a new storage key
+    assert!(!client.get_accepted_tokens().contains(&blend_usdc));
+    client.set_reserve(&admin, &blend_usdc, &ReserveConfig::wallet_only(7));
+    assert!(client.get_accepted_tokens().contains(&blend_usdc));
+    assert_eq!(client.get_reserves().len(), 3);
     
-    // Set Blend USDC token
-    client.set_blend_usdc_token(&blend_usdc);
+    let invalid = ReserveConfig { supply_threshold: -1, ..ReserveConfig::wallet_only(7) };
+    assert_eq!(
+        client.try_set_reserve(&admin, &blend_usdc, &invalid),
+        Err(Ok(PoolError::InvalidAmount))
+    );
     
     // Contract should still be functional
-    let (total_usdc, total_xlm, fees_usdc, fees_xlm, supplied) = client.get_status();
-    assert_eq!(total_usdc, 0);
-    assert_eq!(total_xlm, 0);
-    assert_eq!(fees_usdc, 0);
-    assert_eq!(fees_xlm, 0);
-    assert_eq!(supplied, false);
+    let status = client.get_status(&blend_usdc);
+    assert_eq!(status.total_deposits, 0);
+    assert_eq!(status.fees_earned, 0);
+    assert!(!status.supplied_to_blend);
 }
 
 #[test]
@@ -58,27 +227,1929 @@ fn test_admin_functions() {
     let env = Env::default();
     env.mock_all_auths();
     
-    let contract_id = env.register(PoolContract, ());
-    let client = PoolContractClient::new(&env, &contract_id);
-    
     let admin = Address::generate(&env);
     let seller = Address::generate(&env);
     let new_seller = Address::generate(&env);
-    let usdc_token = Address::generate(&env);
-    let blend_usdc = Address::generate(&env);
+    let usdc_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let blend_usdc = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let xlm_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
     
-    client.initialize(&admin, &seller, &200u32, &usdc_token);
+    let contract_id = env.register(
+        PoolContract,
+        (&admin, &seller, 0u32, 200u32, soroban_sdk::vec![&env, usdc_token.clone(), xlm_token.clone()]),
+    );
+    let client = PoolContractClient::new(&env, &contract_id);
     
-    // Test set_seller
-    client.set_seller(&new_seller);
+    // Test seller change
+    apply_change_now(&env, &client, &ConfigChange::Seller(new_seller));
     
-    // Test set_fee_percent
-    client.set_fee_percent(&300u32);
+    // Test fee terms change
+    let fee_terms = FeeTerms { buyer_surcharge_bps: 100, seller_commission_bps: 100 };
+    apply_change_now(&env, &client, &ConfigChange::FeeTerms(fee_terms));
     
-    // Test set_blend_usdc_token
-    client.set_blend_usdc_token(&blend_usdc);
+    // Test set_reserve
+    client.set_reserve(&admin, &blend_usdc, &ReserveConfig::wallet_only(7));
     
     // All admin functions should succeed with mock auth
-    let (total_usdc, _, _, _, _) = client.get_status();
+    let PoolStatus { total_deposits, .. } = client.get_status(&usdc_token);
+    assert_eq!(total_deposits, 0);
+}
+
+#[test]
+fn test_release_pays_seller_minus_fee() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    assert_eq!(client.get_status(&usdc_token).total_deposits, 100_0000000);
+    
+    let seller_share = client.release(&admin, &1u64);
+    
+    assert_eq!(seller_share, 98_0000000);
+    assert_eq!(usdc.balance(&seller), 98_0000000);
+    assert_eq!(usdc.balance(&client.address), 2_0000000);
+    
+    let PoolStatus { total_deposits: total_usdc, fees_earned: fees_usdc, .. } = client.get_status(&usdc_token);
+    assert_eq!(total_usdc, 0);
+    assert_eq!(fees_usdc, 2_0000000);
+}
+
+#[test]
+fn test_refund_returns_full_amount() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.deposit(&buyer, &usdc_token, &50_0000000, &2u64);
+    
+    let refunded = client.refund(&admin, &1u64);
+    assert_eq!(refunded, 100_0000000);
+    assert_eq!(usdc.balance(&buyer), 950_0000000);
+    
+    // Settlement only pays out the order that is still held
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    assert_eq!(paid, Map::from_array(&env, [(usdc_token.clone(), 49_0000000)]));
+    assert_eq!(usdc.balance(&seller), 49_0000000);
+    
+    let PoolStatus { total_deposits: total_usdc, fees_earned: fees_usdc, .. } = client.get_status(&usdc_token);
     assert_eq!(total_usdc, 0);
+    assert_eq!(fees_usdc, 1_0000000);
+}
+
+#[test]
+fn test_settle_works_through_orders_in_batches() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    for order_id in 1..=4u64 {
+        client.deposit(&buyer, &usdc_token, &10_0000000, &order_id);
+    }
+    client.refund(&admin, &2u64);
+    
+    // Pending payout comes from the ledger, not a scan of held orders
+    assert_eq!(client.get_status(&usdc_token).pending_payout, 29_4000000);
+    
+    // The first batch covers orders 1 and 2; order 2 is already closed
+    assert_eq!(client.settle(&admin, &2u32).get(usdc_token.clone()), Some(9_8000000));
+    assert_eq!(client.get_deposit(&3u64).status, DepositStatus::Held);
+    assert_eq!(client.get_status(&usdc_token).pending_payout, 19_6000000);
+    
+    // The next batch picks up where the last one stopped
+    assert_eq!(client.settle(&admin, &2u32).get(usdc_token.clone()), Some(19_6000000));
+    assert_eq!(client.get_deposit(&4u64).status, DepositStatus::Released);
+    assert_eq!(TokenClient::new(&env, &usdc_token).balance(&seller), 29_4000000);
+    assert_eq!(client.settle(&admin, &2u32), Map::new(&env));
+    
+    let status = client.get_status(&usdc_token);
+    assert_eq!(status.pending_payout, 0);
+    assert_eq!(client.get_ledger(&usdc_token).escrowed_fees, 0);
+}
+
+#[test]
+fn test_full_settle_batch_fits_network_limits() {
+    // Default budget and the network's per-invocation resource limits throughout
+    let env = Env::default();
+    let (client, usdc_token, xlm_token, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    for order_id in 0..MAX_SETTLE_BATCH as u64 {
+        let token = if order_id % 2 == 0 { &usdc_token } else { &xlm_token };
+        client.deposit(&buyer, token, &10_0000000, &(order_id + 1));
+    }
+    
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    assert_eq!(paid.get(usdc_token.clone()), Some(78_4000000));
+    assert_eq!(paid.get(xlm_token.clone()), Some(68_6000000));
+    for order_id in 1..=MAX_SETTLE_BATCH as u64 {
+        assert_eq!(client.get_deposit(&order_id).status, DepositStatus::Released);
+    }
+    assert_eq!(TokenClient::new(&env, &xlm_token).balance(&seller), 68_6000000);
+}
+
+#[test]
+fn test_full_settle_batch_with_blend_fits_footprint_limits() {
+    // Blend's debug diagnostics outgrow the test-only shadow budget, so only
+    // the network's footprint and write limits are enforced here
+    let env = Env::default();
+    let (client, usdc_token, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    // The widest batch: wallet USDC and XLM plus two reserves redeemed from Blend
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_xlm = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &blend_xlm]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    add_blend_reserve(&env, &client, &blend_xlm, &blend_pool);
+    
+    let tokens = [&usdc_token, &xlm_token, &blend_usdc, &blend_xlm];
+    for order_id in 0..MAX_SETTLE_BATCH as u64 {
+        client.deposit(&buyer, tokens[(order_id % 4) as usize], &10_0000000, &(order_id + 1));
+    }
+    client.supply_to_blend(&admin);
+    
+    env.cost_estimate().budget().reset_unlimited();
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    assert_eq!(paid.len(), 4);
+    for order_id in 1..=MAX_SETTLE_BATCH as u64 {
+        assert_eq!(client.get_deposit(&order_id).status, DepositStatus::Released);
+    }
+    let resources = env.cost_estimate().resources();
+    assert!(resources.memory_read_entries + resources.disk_read_entries + resources.write_entries <= 100);
+}
+
+#[test]
+fn test_deferred_order_does_not_block_later_orders() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, usdc_token, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let borrower = Address::generate(&env);
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let collateral = create_token(&env, &borrower, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &collateral]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    // Order 1 sits in Blend; orders 2-10 are plain wallet USDC
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    for order_id in 2..=10u64 {
+        client.deposit(&buyer, &usdc_token, &10_0000000, &order_id);
+    }
+    
+    // A borrower drains the reserve, so order 1 cannot be redeemed
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 2, address: collateral.clone(), amount: 1000_0000000 },
+        pool::Request { request_type: 4, address: blend_usdc.clone(), amount: 90_0000000 },
+    ]);
+    
+    client.settle(&admin, &5u32);
+    assert!(env.events().all().filter_by_contract(&client.address).events().contains(
+        &events::SettlementDeferred { order_id: 1, token: blend_usdc.clone(), seller_share: 98_0000000 }
+            .to_xdr(&env, &client.address)
+    ));
+    client.settle(&admin, &5u32);
+    
+    // Every liquid order is paid; only the illiquid one is left held
+    assert_eq!(client.get_deposit(&1u64).status, DepositStatus::Held);
+    for order_id in 2..=10u64 {
+        assert_eq!(client.get_deposit(&order_id).status, DepositStatus::Released);
+    }
+    assert_eq!(TokenClient::new(&env, &usdc_token).balance(&seller), 88_2000000);
+    assert_eq!(client.settle(&admin, &5u32), Map::new(&env));
+    
+    // Once the borrow is repaid the deferred order is released on its own
+    StellarAssetClient::new(&env, &blend_usdc).mint(&borrower, &100_0000000);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 5, address: blend_usdc.clone(), amount: 100_0000000 },
+    ]);
+    assert_eq!(client.release(&admin, &1u64), 98_0000000);
+}
+
+#[test]
+fn test_fee_terms_are_locked_at_deposit() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    
+    // README split: buyer pays price + 1%, seller receives price - 1%
+    let readme_terms = FeeTerms { buyer_surcharge_bps: 100, seller_commission_bps: 100 };
+    apply_change_now(&env, &client, &ConfigChange::FeeTerms(readme_terms));
+    client.deposit(&buyer, &usdc_token, &101_0000000, &1u64);
+    assert_eq!(
+        client.get_deposit(&1u64).fee_terms,
+        FeeTerms { buyer_surcharge_bps: 100, seller_commission_bps: 100 }
+    );
+    
+    // A later change only applies to new deposits
+    let new_terms = FeeTerms { buyer_surcharge_bps: 0, seller_commission_bps: 500 };
+    apply_change_now(&env, &client, &ConfigChange::FeeTerms(new_terms));
+    client.deposit(&buyer, &usdc_token, &100_0000000, &2u64);
+    
+    assert_eq!(client.release(&admin, &1u64), 99_0000000);
+    assert_eq!(client.get_fees_owed(&usdc_token), 2_0000000);
+    
+    assert_eq!(client.settle(&admin, &MAX_SETTLE_BATCH).get(usdc_token.clone()), Some(95_0000000));
+    assert_eq!(usdc.balance(&seller), 194_0000000);
+    assert_eq!(client.get_fees_owed(&usdc_token), 7_0000000);
+}
+
+#[test]
+fn test_config_changes_are_timelocked() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, usdc_token, xlm_token, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    
+    // Fee terms are capped
+    let too_high = FeeTerms { buyer_surcharge_bps: 0, seller_commission_bps: 501 };
+    assert_eq!(
+        client.try_queue_change(&admin, &ConfigChange::FeeTerms(too_high)),
+        Err(Ok(PoolError::FeeTooHigh))
+    );
+    assert_eq!(client.try_set_max_fee_bps(&admin, &10001u32), Err(Ok(PoolError::FeeTooHigh)));
+    
+    // A queued change cannot apply before its notice period and can be cancelled
+    let new_seller = Address::generate(&env);
+    client.queue_change(&admin, &ConfigChange::Seller(new_seller.clone()));
+    env.ledger().with_mut(|li| li.timestamp += CONFIG_CHANGE_DELAY - 1);
+    assert_eq!(client.try_apply_change(&admin, &ChangeKind::Seller), Err(Ok(PoolError::TimelockActive)));
+    client.cancel_change(&admin, &ChangeKind::Seller);
+    assert_eq!(client.get_queued_change(&ChangeKind::Seller), None);
+    assert_eq!(client.try_apply_change(&admin, &ChangeKind::Seller), Err(Ok(PoolError::NoQueuedChange)));
+    assert_eq!(client.try_cancel_change(&admin, &ChangeKind::Seller), Err(Ok(PoolError::NoQueuedChange)));
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.release(&admin, &1u64);
+    assert_eq!(usdc.balance(&seller), 98_0000000);
+    
+    // Once applied, payouts go to the new seller
+    apply_change_now(&env, &client, &ConfigChange::Seller(new_seller.clone()));
+    client.deposit(&buyer, &usdc_token, &100_0000000, &2u64);
+    client.release(&admin, &2u64);
+    assert_eq!(usdc.balance(&new_seller), 98_0000000);
+    
+    // Lowering the cap blocks a fee change queued under the old cap
+    let fee_terms = FeeTerms { buyer_surcharge_bps: 0, seller_commission_bps: 400 };
+    let queued = client.queue_change(&admin, &ConfigChange::FeeTerms(fee_terms.clone()));
+    assert_eq!(
+        client.get_queued_change(&ChangeKind::FeeTerms),
+        Some(QueuedChange { change: ConfigChange::FeeTerms(fee_terms), effective_at: queued })
+    );
+    client.set_max_fee_bps(&admin, &300u32);
+    env.ledger().with_mut(|li| li.timestamp = queued);
+    assert_eq!(client.try_apply_change(&admin, &ChangeKind::FeeTerms), Err(Ok(PoolError::FeeTooHigh)));
+    
+    // Once the pool has held a token, even its first Blend pool goes through the timelock
+    let pool_a = create_blend_pool(&env, &[&usdc_token]);
+    let pool_b = create_blend_pool(&env, &[&xlm_token, &usdc_token]);
+    let config = ReserveConfig { blend_pool: Some(pool_a.clone()), ..ReserveConfig::wallet_only(7) };
+    assert_eq!(
+        client.try_set_reserve(&admin, &usdc_token, &config),
+        Err(Ok(PoolError::ChangeRequiresTimelock))
+    );
+    apply_change_now(&env, &client, &ConfigChange::BlendPool(usdc_token.clone(), pool_a.clone(), 0));
+    assert_eq!(client.get_reserve(&usdc_token), Some(config.clone()));
+    let moved = ReserveConfig { blend_pool: Some(pool_b.clone()), ..config.clone() };
+    assert_eq!(
+        client.try_set_reserve(&admin, &usdc_token, &moved),
+        Err(Ok(PoolError::ChangeRequiresTimelock))
+    );
+    let unlisted = ReserveConfig { blend_pool: None, ..config.clone() };
+    assert_eq!(
+        client.try_set_reserve(&admin, &usdc_token, &unlisted),
+        Err(Ok(PoolError::ChangeRequiresTimelock))
+    );
+    
+    // Other settings can still change directly
+    let config = ReserveConfig { supply_threshold: 5_0000000, ..config };
+    client.set_reserve(&admin, &usdc_token, &config);
+    
+    assert_eq!(
+        client.try_queue_change(&admin, &ConfigChange::BlendPool(Address::generate(&env), pool_b.clone(), 0)),
+        Err(Ok(PoolError::UnsupportedToken))
+    );
+    // The new pool must list the token at the given index
+    assert_eq!(
+        client.try_queue_change(&admin, &ConfigChange::BlendPool(usdc_token.clone(), pool_b.clone(), 0)),
+        Err(Ok(PoolError::NotBlendReserve))
+    );
+    apply_change_now(&env, &client, &ConfigChange::BlendPool(usdc_token.clone(), pool_b.clone(), 1));
+    assert_eq!(
+        client.get_reserve(&usdc_token),
+        Some(ReserveConfig { blend_pool: Some(pool_b), reserve_index: 1, ..config })
+    );
+}
+
+#[test]
+fn test_blend_pool_change_requires_empty_position() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let pool_a = create_blend_pool(&env, &[&blend_usdc]);
+    let pool_b = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &pool_a);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    assert!(client.get_ledger(&blend_usdc).b_tokens > 0);
+    
+    // Switching pools with shares still supplied would orphan them
+    client.queue_change(&admin, &ConfigChange::BlendPool(blend_usdc.clone(), pool_b.clone(), 0));
+    env.ledger().with_mut(|li| li.timestamp += CONFIG_CHANGE_DELAY);
+    assert_eq!(
+        client.try_apply_change(&admin, &ChangeKind::BlendPool),
+        Err(Ok(PoolError::MigrationRequired))
+    );
+    assert_eq!(client.get_reserve(&blend_usdc).unwrap().blend_pool, Some(pool_a));
+    
+    // Once the position is withdrawn the queued change goes through
+    client.pause(&admin);
+    client.emergency_withdraw_from_blend(&admin);
+    assert_eq!(client.get_ledger(&blend_usdc).b_tokens, 0);
+    client.apply_change(&admin, &ChangeKind::BlendPool);
+    assert_eq!(client.get_reserve(&blend_usdc).unwrap().blend_pool, Some(pool_b));
+}
+
+#[test]
+fn test_roles_gate_privileged_entry_points() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let backend = Address::generate(&env);
+    
+    client.grant_role(&Role::Settler, &backend);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::RoleGranted { role: Role::Settler, account: backend.clone() }
+            .to_xdr(&env, &client.address)]
+    );
+    assert!(client.has_role(&Role::Settler, &backend));
+    assert!(!client.has_role(&Role::Treasurer, &backend));
+    
+    // The settler key can move orders but not fees, config or code
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.deposit(&buyer, &usdc_token, &50_0000000, &2u64);
+    client.release(&backend, &1u64);
+    assert_eq!(
+        client.try_withdraw_fees(&backend, &usdc_token, &backend),
+        Err(Ok(PoolError::Unauthorized))
+    );
+    assert_eq!(
+        client.try_queue_change(&backend, &ConfigChange::Seller(backend.clone())),
+        Err(Ok(PoolError::Unauthorized))
+    );
+    assert_eq!(
+        client.try_upgrade(&backend, &BytesN::random(&env)),
+        Err(Ok(PoolError::Unauthorized))
+    );
+    
+    // The admin can hand out and take back roles
+    let treasurer = Address::generate(&env);
+    client.grant_role(&Role::Treasurer, &treasurer);
+    assert_eq!(client.withdraw_fees(&treasurer, &usdc_token, &treasurer), 2_0000000);
+    
+    client.revoke_role(&Role::Settler, &backend);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::RoleRevoked { role: Role::Settler, account: backend.clone() }
+            .to_xdr(&env, &client.address)]
+    );
+    assert_eq!(client.try_settle(&backend, &MAX_SETTLE_BATCH), Err(Ok(PoolError::Unauthorized)));
+    assert_eq!(client.settle(&admin, &MAX_SETTLE_BATCH), Map::from_array(&env, [(usdc_token.clone(), 49_0000000)]));
+    assert_eq!(TokenClient::new(&env, &usdc_token).balance(&seller), 147_0000000);
+}
+
+#[test]
+fn test_grant_role_requires_admin_auth() {
+    let env = Env::default();
+    let (client, _, _, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let intruder = Address::generate(&env);
+    
+    env.mock_auths(&[MockAuth {
+        address: &intruder,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "grant_role",
+            args: (Role::Upgrader, intruder.clone()).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_grant_role(&Role::Upgrader, &intruder).is_err());
+    
+    env.mock_auths(&[MockAuth {
+        address: &admin,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "grant_role",
+            args: (Role::Upgrader, intruder.clone()).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    client.grant_role(&Role::Upgrader, &intruder);
+    assert!(client.has_role(&Role::Upgrader, &intruder));
+}
+
+/// Authorize exactly one call to `fn_name` by `signer`
+fn mock_single_auth(env: &Env, client: &PoolContractClient, signer: &Address, fn_name: &str, args: Vec<Val>) {
+    env.mock_auths(&[MockAuth {
+        address: signer,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name,
+            args,
+            sub_invokes: &[],
+        },
+    }]);
+}
+
+#[test]
+fn test_two_step_admin_transfer() {
+    let env = Env::default();
+    let (client, _, _, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let new_admin = Address::generate(&env);
+    let intruder = Address::generate(&env);
+    
+    // Only the current admin can propose
+    mock_single_auth(&env, &client, &intruder, "propose_admin", (new_admin.clone(),).into_val(&env));
+    assert!(client.try_propose_admin(&new_admin).is_err());
+    
+    mock_single_auth(&env, &client, &admin, "propose_admin", (new_admin.clone(),).into_val(&env));
+    let deadline = client.propose_admin(&new_admin);
+    assert_eq!(
+        client.get_pending_admin(),
+        Some(PendingAdmin { new_admin: new_admin.clone(), deadline })
+    );
+    assert_eq!(client.get_admin(), admin);
+    
+    // Neither the old admin nor a third party can accept on the new admin's behalf
+    mock_single_auth(&env, &client, &admin, "accept_admin", ().into_val(&env));
+    assert!(client.try_accept_admin().is_err());
+    mock_single_auth(&env, &client, &intruder, "accept_admin", ().into_val(&env));
+    assert!(client.try_accept_admin().is_err());
+    
+    mock_single_auth(&env, &client, &new_admin, "accept_admin", ().into_val(&env));
+    client.accept_admin();
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address).events().last(),
+        Some(&events::AdminTransferred { previous: admin.clone(), new_admin: new_admin.clone() }
+            .to_xdr(&env, &client.address))
+    );
+    assert_eq!(client.get_admin(), new_admin);
+    assert_eq!(client.get_pending_admin(), None);
+    
+    // The old admin's roles move with the admin seat
+    assert!(!client.has_role(&Role::Upgrader, &admin));
+    assert!(client.has_role(&Role::Upgrader, &new_admin));
+    assert!(!client.has_role(&Role::Treasurer, &admin));
+    assert!(client.has_role(&Role::Treasurer, &new_admin));
+    
+    // The new admin now manages roles; the old one cannot
+    mock_single_auth(&env, &client, &admin, "grant_role", (Role::Pauser, intruder.clone()).into_val(&env));
+    assert!(client.try_grant_role(&Role::Pauser, &intruder).is_err());
+    mock_single_auth(&env, &client, &new_admin, "grant_role", (Role::Pauser, intruder.clone()).into_val(&env));
+    client.grant_role(&Role::Pauser, &intruder);
+    assert!(client.has_role(&Role::Pauser, &intruder));
+}
+
+#[test]
+fn test_admin_transfer_expires_and_can_be_cancelled() {
+    let env = Env::default();
+    let (client, _, _, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let new_admin = Address::generate(&env);
+    
+    mock_single_auth(&env, &client, &new_admin, "accept_admin", ().into_val(&env));
+    assert_eq!(client.try_accept_admin(), Err(Ok(PoolError::NoPendingTransfer)));
+    
+    mock_single_auth(&env, &client, &admin, "propose_admin", (new_admin.clone(),).into_val(&env));
+    client.propose_admin(&new_admin);
+    env.ledger().with_mut(|li| li.timestamp += ADMIN_TRANSFER_WINDOW + 1);
+    
+    mock_single_auth(&env, &client, &new_admin, "accept_admin", ().into_val(&env));
+    assert_eq!(client.try_accept_admin(), Err(Ok(PoolError::TransferExpired)));
+    assert_eq!(client.get_admin(), admin);
+    
+    // A fresh proposal can be withdrawn before it is accepted
+    mock_single_auth(&env, &client, &admin, "propose_admin", (new_admin.clone(),).into_val(&env));
+    client.propose_admin(&new_admin);
+    mock_single_auth(&env, &client, &admin, "cancel_admin_transfer", ().into_val(&env));
+    client.cancel_admin_transfer();
+    
+    mock_single_auth(&env, &client, &new_admin, "accept_admin", ().into_val(&env));
+    assert_eq!(client.try_accept_admin(), Err(Ok(PoolError::NoPendingTransfer)));
+    assert_eq!(client.get_admin(), admin);
+}
+
+#[test]
+fn test_guardian_recovers_admin_after_delay() {
+    let env = Env::default();
+    let (client, _, _, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let guardian = Address::generate(&env);
+    let new_admin = Address::generate(&env);
+    
+    // No guardian configured yet
+    mock_single_auth(&env, &client, &guardian, "initiate_recovery", (new_admin.clone(),).into_val(&env));
+    assert_eq!(client.try_initiate_recovery(&new_admin), Err(Ok(PoolError::NoGuardian)));
+    
+    let guardian_arg = Some(guardian.clone());
+    mock_single_auth(&env, &client, &guardian, "set_guardian", (guardian_arg.clone(),).into_val(&env));
+    assert!(client.try_set_guardian(&guardian_arg).is_err());
+    mock_single_auth(&env, &client, &admin, "set_guardian", (guardian_arg.clone(),).into_val(&env));
+    client.set_guardian(&guardian_arg);
+    assert_eq!(client.get_guardian(), guardian_arg);
+    
+    // Only the guardian can start a recovery
+    mock_single_auth(&env, &client, &admin, "initiate_recovery", (new_admin.clone(),).into_val(&env));
+    assert!(client.try_initiate_recovery(&new_admin).is_err());
+    mock_single_auth(&env, &client, &guardian, "initiate_recovery", (new_admin.clone(),).into_val(&env));
+    let deadline = client.initiate_recovery(&new_admin);
+    assert_eq!(deadline, env.ledger().timestamp() + GUARDIAN_RECOVERY_DELAY);
+    
+    mock_single_auth(&env, &client, &guardian, "complete_recovery", ().into_val(&env));
+    assert_eq!(client.try_complete_recovery(), Err(Ok(PoolError::TimelockActive)));
+    
+    env.ledger().with_mut(|li| li.timestamp = deadline);
+    mock_single_auth(&env, &client, &new_admin, "complete_recovery", ().into_val(&env));
+    assert!(client.try_complete_recovery().is_err());
+    mock_single_auth(&env, &client, &guardian, "complete_recovery", ().into_val(&env));
+    client.complete_recovery();
+    assert_eq!(client.get_admin(), new_admin);
+    assert_eq!(client.get_pending_recovery(), None);
+    assert!(!client.has_role(&Role::Upgrader, &admin));
+    assert!(client.has_role(&Role::Upgrader, &new_admin));
+}
+
+#[test]
+fn test_admin_can_cancel_guardian_recovery() {
+    let env = Env::default();
+    let (client, _, _, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let guardian = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    
+    mock_single_auth(&env, &client, &admin, "set_guardian", (Some(guardian.clone()),).into_val(&env));
+    client.set_guardian(&Some(guardian.clone()));
+    mock_single_auth(&env, &client, &guardian, "initiate_recovery", (attacker.clone(),).into_val(&env));
+    client.initiate_recovery(&attacker);
+    
+    mock_single_auth(&env, &client, &guardian, "cancel_recovery", ().into_val(&env));
+    assert!(client.try_cancel_recovery().is_err());
+    mock_single_auth(&env, &client, &admin, "cancel_recovery", ().into_val(&env));
+    client.cancel_recovery();
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::RecoveryCancelled {}.to_xdr(&env, &client.address)]
+    );
+    
+    env.ledger().with_mut(|li| li.timestamp += GUARDIAN_RECOVERY_DELAY);
+    mock_single_auth(&env, &client, &guardian, "complete_recovery", ().into_val(&env));
+    assert_eq!(client.try_complete_recovery(), Err(Ok(PoolError::NoPendingTransfer)));
+    assert_eq!(client.get_admin(), admin);
+}
+
+#[test]
+fn test_release_after_refund_fails() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.refund(&admin, &1u64);
+    
+    assert_eq!(client.try_release(&admin, &1u64), Err(Ok(PoolError::OrderNotHeld)));
+    assert_eq!(client.try_refund(&admin, &2u64), Err(Ok(PoolError::OrderNotFound)));
+}
+
+#[test]
+fn test_reclaim_after_expiry() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    
+    client.set_escrow_period(&admin, &3600u64);
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    
+    env.ledger().with_mut(|li| li.timestamp += 3600);
+    
+    let reclaimed = client.reclaim(&1u64);
+    assert_eq!(reclaimed, 100_0000000);
+    assert_eq!(usdc.balance(&buyer), 1000_0000000);
+    assert_eq!(client.get_status(&usdc_token).total_deposits, 0);
+}
+
+#[test]
+fn test_reclaim_before_expiry_fails() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    client.set_escrow_period(&admin, &3600u64);
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    
+    env.ledger().with_mut(|li| li.timestamp += 3599);
+    assert_eq!(client.try_reclaim(&1u64), Err(Ok(PoolError::EscrowNotExpired)));
+}
+
+#[test]
+fn test_escrow_period_is_bounded() {
+    let env = Env::default();
+    let (client, _, _, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    // Instant reclaims and periods that overflow `expires_at` are rejected
+    assert_eq!(client.try_set_escrow_period(&admin, &0u64), Err(Ok(PoolError::InvalidAmount)));
+    assert_eq!(client.try_set_escrow_period(&admin, &3599u64), Err(Ok(PoolError::InvalidAmount)));
+    assert_eq!(client.try_set_escrow_period(&admin, &u64::MAX), Err(Ok(PoolError::InvalidAmount)));
+    client.set_escrow_period(&admin, &(365 * 24 * 60 * 60));
+}
+
+#[test]
+fn test_settle_withdraws_from_blend() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    // The keeper supplies the deposit into Blend
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    let usdc = TokenClient::new(&env, &blend_usdc);
+    assert_eq!(usdc.balance(&client.address), 0);
+    
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    
+    assert_eq!(paid, Map::from_array(&env, [(blend_usdc.clone(), 98_0000000)]));
+    assert_eq!(usdc.balance(&seller), 98_0000000);
+    assert_eq!(client.get_status(&blend_usdc).total_deposits, 0);
+}
+
+#[test]
+fn test_refund_withdraws_from_blend() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.refund(&admin, &1u64);
+    
+    assert_eq!(TokenClient::new(&env, &blend_usdc).balance(&buyer), 1000_0000000);
+}
+
+#[test]
+fn test_settle_defers_orders_without_blend_liquidity() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let borrower = Address::generate(&env);
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let collateral = create_token(&env, &borrower, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &collateral]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &blend_usdc, &40_0000000, &2u64);
+    client.supply_to_blend(&admin);
+    
+    // A borrower takes 80 of the 140 supplied; at 95% max utilization
+    // only ~55.8 can still be withdrawn
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 2, address: collateral.clone(), amount: 1000_0000000 },
+        pool::Request { request_type: 4, address: blend_usdc.clone(), amount: 80_0000000 },
+    ]);
+    
+    // Only the order that fits in the free liquidity is paid out
+    assert_eq!(client.settle(&admin, &MAX_SETTLE_BATCH).get(blend_usdc.clone()), Some(39_2000000));
+    assert_eq!(TokenClient::new(&env, &blend_usdc).balance(&seller), 39_2000000);
+    assert_eq!(client.get_status(&blend_usdc).total_deposits, 100_0000000);
+}
+
+#[test]
+fn test_status_reports_blend_position_and_yield() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let borrower = Address::generate(&env);
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let collateral = create_token(&env, &borrower, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &collateral]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    
+    let status = client.get_status(&blend_usdc);
+    assert_eq!(status.total_deposits, 100_0000000);
+    assert_eq!(status.pending_payout, 98_0000000);
+    assert!(status.supplied_to_blend);
+    assert!(status.blend_position >= 99_9999999);
+    assert_eq!(status.accrued_yield, 0);
+    
+    // Interest on a borrow accrues to our supply position
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 2, address: collateral.clone(), amount: 1000_0000000 },
+        pool::Request { request_type: 4, address: blend_usdc.clone(), amount: 50_0000000 },
+    ]);
+    env.ledger().with_mut(|li| li.timestamp += 365 * 24 * 60 * 60);
+    
+    let status = client.get_status(&blend_usdc);
+    assert!(status.blend_position > 100_0000000);
+    assert_eq!(status.accrued_yield, status.blend_position - status.total_deposits);
+    assert_eq!(status.pending_payout, 98_0000000);
+}
+
+#[test]
+fn test_yield_tracks_b_token_shares() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let borrower = Address::generate(&env);
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let collateral = create_token(&env, &borrower, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &collateral]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &2u64);
+    client.supply_to_blend(&admin);
+    
+    let report = client.get_yield(&blend_usdc);
+    assert_eq!(report.cost_basis, 200_0000000);
+    assert!(report.b_tokens > 0);
+    assert_eq!(report.realized_yield, 0);
+    assert!(report.unrealized_yield <= 0 && report.unrealized_yield > -2);
+    
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 2, address: collateral.clone(), amount: 1000_0000000 },
+        pool::Request { request_type: 4, address: blend_usdc.clone(), amount: 50_0000000 },
+    ]);
+    env.ledger().with_mut(|li| li.timestamp += 365 * 24 * 60 * 60);
+    
+    let accrued = client.get_yield(&blend_usdc);
+    assert!(accrued.b_rate > report.b_rate);
+    assert_eq!(accrued.b_tokens, report.b_tokens);
+    assert!(accrued.unrealized_yield > 0);
+    
+    // Refunding one order withdraws principal plus part of the interest
+    client.refund(&admin, &1u64);
+    let after = client.get_yield(&blend_usdc);
+    assert!(after.realized_yield > 0);
+    assert!(after.cost_basis < 200_0000000 && after.cost_basis > 100_0000000);
+    assert!((after.realized_yield + after.unrealized_yield - accrued.unrealized_yield).abs() <= 2);
+    
+    // Circle USDC is never supplied to Blend
+    assert_eq!(client.try_get_yield(&usdc_token), Err(Ok(PoolError::BlendPoolNotConfigured)));
+}
+
+#[test]
+fn test_withdraw_fees_is_bounded_by_fee_ledger() {
+    let env = Env::default();
+    let (client, usdc_token, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    let treasury = Address::generate(&env);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.deposit(&buyer, &usdc_token, &50_0000000, &2u64);
+    client.deposit(&buyer, &xlm_token, &200_0000000, &3u64);
+    client.release(&admin, &1u64);
+    client.release(&admin, &3u64);
+    
+    // Only the fee leaves; order 2 stays fully escrowed
+    assert_eq!(client.get_fees_owed(&usdc_token), 2_0000000);
+    assert_eq!(client.withdraw_fees(&admin, &usdc_token, &treasury), 2_0000000);
+    assert_eq!(usdc.balance(&treasury), 2_0000000);
+    assert_eq!(usdc.balance(&client.address), 50_0000000);
+    assert_eq!(client.get_fees_owed(&usdc_token), 0);
+    assert_eq!(client.withdraw_fees(&admin, &usdc_token, &treasury), 0);
+    
+    assert_eq!(client.withdraw_fees(&admin, &xlm_token, &treasury), 4_0000000);
+    assert_eq!(TokenClient::new(&env, &xlm_token).balance(&treasury), 4_0000000);
+    
+    assert_eq!(client.get_status(&usdc_token).fees_earned, 0);
+    assert_eq!(client.get_status(&xlm_token).fees_earned, 0);
+    
+    client.refund(&admin, &2u64);
+    assert_eq!(usdc.balance(&buyer), 900_0000000);
 }
+
+#[test]
+fn test_withdraw_fees_never_touches_escrow() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let treasury = Address::generate(&env);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.deposit(&buyer, &usdc_token, &50_0000000, &2u64);
+    client.release(&admin, &1u64);
+    
+    // Even an overstated fee ledger can only pay out the surplus above escrow
+    let mut ledger = client.get_ledger(&usdc_token);
+    ledger.fees_owed = 1000_0000000;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&DataKey::Ledger(usdc_token.clone()), &ledger);
+    });
+    assert_eq!(client.withdraw_fees(&admin, &usdc_token, &treasury), 2_0000000);
+    assert_eq!(client.get_fees_owed(&usdc_token), 998_0000000);
+    assert_eq!(
+        client.try_withdraw_fees(&admin, &usdc_token, &treasury),
+        Err(Ok(PoolError::EscrowShortfall))
+    );
+    assert_eq!(TokenClient::new(&env, &usdc_token).balance(&client.address), 50_0000000);
+    
+    // Emissions are only credited to the fee ledger once BLND is configured
+    let blend_pool = create_blend_pool(&env, &[&usdc_token]);
+    apply_change_now(&env, &client, &ConfigChange::BlendPool(usdc_token.clone(), blend_pool.clone(), 0));
+    assert_eq!(
+        client.try_claim_emissions(&admin, &blend_pool, &soroban_sdk::vec![&env, 1u32]),
+        Err(Ok(PoolError::BlndTokenNotConfigured))
+    );
+}
+
+#[test]
+fn test_withdraw_yield_pays_out_blend_interest() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let treasury = Address::generate(&env);
+    
+    let borrower = Address::generate(&env);
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let collateral = create_token(&env, &borrower, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &collateral]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    let usdc = TokenClient::new(&env, &blend_usdc);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &2u64);
+    client.supply_to_blend(&admin);
+    
+    // Nothing above what the pool owes yet
+    assert_eq!(client.withdraw_yield(&admin, &blend_usdc, &treasury), 0);
+    
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 2, address: collateral.clone(), amount: 1000_0000000 },
+        pool::Request { request_type: 4, address: blend_usdc.clone(), amount: 50_0000000 },
+    ]);
+    env.ledger().with_mut(|li| li.timestamp += 365 * 24 * 60 * 60);
+    
+    // Realize part of the interest by refunding one order through Blend
+    client.refund(&admin, &1u64);
+    assert!(client.get_yield(&blend_usdc).realized_yield > 0);
+    
+    let outsider = Address::generate(&env);
+    assert_eq!(
+        client.try_withdraw_yield(&outsider, &blend_usdc, &treasury),
+        Err(Ok(PoolError::Unauthorized))
+    );
+    
+    // Realized and still-supplied interest both leave, owed funds stay
+    let surplus = client.reconcile().get_unchecked(0).surplus;
+    assert!(surplus > 0);
+    let paid = client.withdraw_yield(&admin, &blend_usdc, &treasury);
+    assert_eq!(paid, surplus);
+    assert_eq!(usdc.balance(&treasury), paid);
+    
+    let after = client.reconcile().get_unchecked(0);
+    assert!(after.surplus >= -1 && after.surplus <= 0);
+    assert_eq!(after.liabilities, 100_0000000);
+    
+    // Once the borrow is repaid the remaining order still settles in full
+    usdc.transfer(&buyer, &borrower, &100_0000000);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 5, address: blend_usdc.clone(), amount: 100_0000000 },
+    ]);
+    client.release(&admin, &2u64);
+    client.settle(&admin, &MAX_SETTLE_BATCH);
+    assert_eq!(usdc.balance(&seller), 98_0000000);
+}
+
+#[test]
+fn test_ledger_tracks_every_entry_point() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &blend_usdc, &50_0000000, &2u64);
+    client.deposit(&buyer, &blend_usdc, &30_0000000, &3u64);
+    client.supply_to_blend(&admin);
+    
+    let ledger = client.get_ledger(&blend_usdc);
+    assert_eq!(ledger.escrowed, 180_0000000);
+    assert_eq!(ledger.supplied, 180_0000000);
+    assert!(ledger.b_tokens > 0);
+    assert_eq!(ledger.fees_owed, 0);
+    
+    client.refund(&admin, &3u64);
+    let ledger = client.get_ledger(&blend_usdc);
+    assert_eq!(ledger.escrowed, 150_0000000);
+    assert_eq!(ledger.supplied, 150_0000000);
+    
+    // settle returns exactly what reached the seller
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    let usdc = TokenClient::new(&env, &blend_usdc);
+    assert_eq!(paid, Map::from_array(&env, [(blend_usdc.clone(), 147_0000000)]));
+    assert_eq!(usdc.balance(&seller), 147_0000000);
+    
+    let ledger = client.get_ledger(&blend_usdc);
+    assert_eq!(ledger.escrowed, 0);
+    assert_eq!(ledger.seller_payable, 0);
+    assert_eq!(ledger.fees_owed, 3_0000000);
+    
+    // Only the seller shares were withdrawn; the fees stay supplied
+    let status = client.get_status(&blend_usdc);
+    assert_eq!(status.total_deposits, 0);
+    assert_eq!(status.fees_earned, 3_0000000);
+    assert!(status.blend_position >= 2_9999999);
+}
+
+#[test]
+fn test_reconcile_flags_shortfall() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, usdc_token, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &xlm_token]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    add_blend_reserve(&env, &client, &xlm_token, &blend_pool);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.deposit(&buyer, &blend_usdc, &200_0000000, &2u64);
+    client.deposit(&buyer, &xlm_token, &300_0000000, &3u64);
+    client.supply_to_blend(&admin);
+    client.release(&admin, &1u64);
+    
+    let report = client.reconcile();
+    assert_eq!(report.len(), 3);
+    let entry = |report: &Vec<Reconciliation>, token: &Address| {
+        report.iter().find(|r| r.token == *token).unwrap()
+    };
+    
+    let circle = entry(&report, &usdc_token);
+    assert_eq!(circle.liabilities, 2_0000000);
+    assert_eq!(circle.wallet_balance, 2_0000000);
+    assert_eq!(circle.surplus, 0);
+    assert!(!circle.shortfall);
+    
+    // Blend USDC lives in the Blend position rather than the wallet
+    let blend = entry(&report, &blend_usdc);
+    assert_eq!(blend.liabilities, 200_0000000);
+    assert_eq!(blend.wallet_balance, 0);
+    assert_eq!(blend.blend_position, 200_0000000);
+    assert_eq!(blend.surplus, 0);
+    assert!(!blend.shortfall);
+    
+    let xlm = entry(&report, &xlm_token);
+    assert_eq!(xlm.liabilities, 300_0000000);
+    assert_eq!(xlm.blend_position, 300_0000000);
+    assert!(!xlm.shortfall);
+    
+    // Liabilities the holdings cannot cover are flagged
+    let mut ledger = client.get_ledger(&xlm_token);
+    ledger.escrowed += 1_0000000;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&DataKey::Ledger(xlm_token.clone()), &ledger);
+    });
+    let xlm = entry(&client.reconcile(), &xlm_token);
+    assert_eq!(xlm.surplus, -1_0000000);
+    assert!(xlm.shortfall);
+}
+
+#[test]
+fn test_get_deposit_and_deposits_by_buyer() {
+    let env = Env::default();
+    let (client, usdc_token, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let other_buyer = Address::generate(&env);
+    StellarAssetClient::new(&env, &usdc_token).mint(&other_buyer, &100_0000000);
+    
+    client.deposit(&buyer, &usdc_token, &10_0000000, &1u64);
+    client.deposit(&other_buyer, &usdc_token, &20_0000000, &2u64);
+    client.deposit(&buyer, &xlm_token, &30_0000000, &3u64);
+    client.release(&admin, &1u64);
+    
+    let deposit = client.get_deposit(&1u64);
+    assert_eq!(deposit.buyer, buyer);
+    assert_eq!(deposit.amount, 10_0000000);
+    assert_eq!(deposit.status, DepositStatus::Released);
+    assert_eq!(client.try_get_deposit(&4u64), Err(Ok(PoolError::OrderNotFound)));
+    
+    let page = client.get_deposits_by_buyer(&buyer, &0u64, &10u32);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get(0).unwrap().order_id, 1);
+    assert_eq!(page.get(1).unwrap().order_id, 3);
+    
+    let page = client.get_deposits_by_buyer(&buyer, &1u64, &1u32);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap().order_id, 3);
+    
+    let page = client.get_deposits_by_buyer(&other_buyer, &0u64, &10u32);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap().order_id, 2);
+    
+    assert_eq!(client.get_deposits_by_buyer(&Address::generate(&env), &0u64, &10u32).len(), 0);
+}
+
+#[test]
+fn test_settle_pays_xlm_to_seller() {
+    let env = Env::default();
+    let (client, usdc_token, xlm_token, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    let xlm = TokenClient::new(&env, &xlm_token);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    client.deposit(&buyer, &xlm_token, &500_0000000, &2u64);
+    
+    assert_eq!(client.get_status(&usdc_token).total_deposits, 100_0000000);
+    assert_eq!(client.get_status(&xlm_token).total_deposits, 500_0000000);
+    
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    
+    assert_eq!(paid.get(usdc_token.clone()), Some(98_0000000));
+    assert_eq!(paid.get(xlm_token.clone()), Some(490_0000000));
+    assert_eq!(usdc.balance(&seller), 98_0000000);
+    assert_eq!(xlm.balance(&seller), 490_0000000);
+    assert_eq!(xlm.balance(&client.address), 10_0000000);
+    
+    let PoolStatus { total_deposits: total_usdc, fees_earned: fees_usdc, .. } = client.get_status(&usdc_token);
+    assert_eq!(total_usdc, 0);
+    assert_eq!(fees_usdc, 2_0000000);
+    let PoolStatus { total_deposits: total_xlm, fees_earned: fees_xlm, .. } = client.get_status(&xlm_token);
+    assert_eq!(total_xlm, 0);
+    assert_eq!(fees_xlm, 10_0000000);
+}
+
+#[test]
+fn test_release_and_refund_xlm_orders() {
+    let env = Env::default();
+    let (client, _, xlm_token, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let xlm = TokenClient::new(&env, &xlm_token);
+    
+    client.deposit(&buyer, &xlm_token, &200_0000000, &1u64);
+    client.deposit(&buyer, &xlm_token, &300_0000000, &2u64);
+    
+    assert_eq!(client.release(&admin, &1u64), 196_0000000);
+    assert_eq!(client.refund(&admin, &2u64), 300_0000000);
+    
+    assert_eq!(xlm.balance(&seller), 196_0000000);
+    assert_eq!(xlm.balance(&buyer), 800_0000000);
+    
+    let PoolStatus { total_deposits: total_xlm, fees_earned: fees_xlm, .. } = client.get_status(&xlm_token);
+    assert_eq!(total_xlm, 0);
+    assert_eq!(fees_xlm, 4_0000000);
+    
+    // Nothing left to settle
+    assert_eq!(client.settle(&admin, &MAX_SETTLE_BATCH).len(), 0);
+}
+
+#[test]
+fn test_settle_withdraws_xlm_from_blend() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, xlm_token, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let xlm = TokenClient::new(&env, &xlm_token);
+    
+    let blend_pool = create_blend_pool(&env, &[&xlm_token]);
+    add_blend_reserve(&env, &client, &xlm_token, &blend_pool);
+    
+    // XLM deposit is supplied to Blend through the configured SAC
+    client.deposit(&buyer, &xlm_token, &500_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    assert_eq!(xlm.balance(&client.address), 0);
+    
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    
+    assert_eq!(paid.get(xlm_token.clone()), Some(490_0000000));
+    assert_eq!(xlm.balance(&seller), 490_0000000);
+}
+
+#[test]
+fn test_deposit_rejects_unknown_token() {
+    let env = Env::default();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let other_token = create_token(&env, &buyer, 1000_0000000);
+    let result = client.try_deposit(&buyer, &other_token, &100_0000000, &1u64);
+    assert_eq!(result, Err(Ok(PoolError::UnsupportedToken)));
+    
+    // Once it has a reserve config the token is accepted
+    client.set_reserve(&admin, &other_token, &ReserveConfig::wallet_only(7));
+    client.deposit(&buyer, &other_token, &100_0000000, &1u64);
+    assert_eq!(client.get_status(&other_token).total_deposits, 100_0000000);
+}
+
+#[test]
+fn test_deposit_rejects_delisted_token() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let delisted = ReserveConfig { accepted: false, ..client.get_reserve(&usdc_token).unwrap() };
+    client.set_reserve(&admin, &usdc_token, &delisted);
+    assert_eq!(client.get_accepted_tokens().len(), 1);
+    
+    let result = client.try_deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    assert_eq!(result, Err(Ok(PoolError::UnsupportedToken)));
+}
+
+#[test]
+fn test_typed_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+    
+    let admin = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let usdc_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let xlm_token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    
+    let contract_id = env.register(
+        PoolContract,
+        (&admin, &seller, 0u32, 200u32, soroban_sdk::vec![&env, usdc_token.clone(), xlm_token.clone()]),
+    );
+    let client = PoolContractClient::new(&env, &contract_id);
+    
+    let result = client.try_deposit(&admin, &usdc_token, &0, &1u64);
+    assert_eq!(result, Err(Ok(PoolError::InvalidAmount)));
+    
+    let unknown_pool = Address::generate(&env);
+    let result = client.try_claim_emissions(&admin, &unknown_pool, &soroban_sdk::vec![&env, 0u32]);
+    assert_eq!(result, Err(Ok(PoolError::BlendPoolNotConfigured)));
+}
+
+#[test]
+fn test_deposit_rejects_duplicate_order() {
+    let env = Env::default();
+    let (client, usdc_token, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    
+    let result = client.try_deposit(&buyer, &usdc_token, &50_0000000, &1u64);
+    assert_eq!(result, Err(Ok(PoolError::DuplicateOrder)));
+    let result = client.try_deposit(&buyer, &xlm_token, &50_0000000, &1u64);
+    assert_eq!(result, Err(Ok(PoolError::DuplicateOrder)));
+    
+    // Closed orders can't be paid again either
+    client.refund(&admin, &1u64);
+    let result = client.try_deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    assert_eq!(result, Err(Ok(PoolError::DuplicateOrder)));
+    
+    // Neither the balance nor the totals moved for the rejected deposits
+    assert_eq!(usdc.balance(&buyer), 1000_0000000);
+    assert_eq!(client.get_status(&usdc_token).total_deposits, 0);
+}
+
+#[test]
+fn test_deposits_are_paginated() {
+    let env = Env::default();
+    let (client, usdc_token, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    client.deposit(&buyer, &usdc_token, &10_0000000, &101u64);
+    client.deposit(&buyer, &xlm_token, &20_0000000, &102u64);
+    client.deposit(&buyer, &usdc_token, &30_0000000, &103u64);
+    client.refund(&admin, &102u64);
+    
+    assert_eq!(client.get_deposit_count(), 3);
+    
+    let first_page = client.get_deposits(&0u64, &2u32);
+    assert_eq!(first_page.len(), 2);
+    assert_eq!(first_page.get(0).unwrap().order_id, 101);
+    assert_eq!(first_page.get(1).unwrap().status, DepositStatus::Refunded);
+    
+    let second_page = client.get_deposits(&2u64, &2u32);
+    assert_eq!(second_page.len(), 1);
+    assert_eq!(second_page.get(0).unwrap().order_id, 103);
+    
+    assert_eq!(client.get_deposits(&3u64, &2u32).len(), 0);
+}
+
+#[test]
+fn test_full_page_fits_network_limits() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    StellarAssetClient::new(&env, &usdc_token).mint(&buyer, &1000_0000000);
+    
+    for order_id in 1..=MAX_PAGE_SIZE as u64 + 1 {
+        client.deposit(&buyer, &usdc_token, &10_0000000, &order_id);
+    }
+    
+    // Oversized requests are cut to a page the network's footprint limit allows
+    let page = client.get_deposits(&0u64, &100u32);
+    assert_eq!(page.len(), MAX_PAGE_SIZE);
+    assert_eq!(page.last().unwrap().order_id, MAX_PAGE_SIZE as u64);
+    let page = client.get_deposits_by_buyer(&buyer, &1u64, &100u32);
+    assert_eq!(page.len(), MAX_PAGE_SIZE);
+    assert_eq!(page.last().unwrap().order_id, MAX_PAGE_SIZE as u64 + 1);
+}
+
+#[test]
+fn test_deposits_use_persistent_entries() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    
+    client.deposit(&buyer, &usdc_token, &10_0000000, &1u64);
+    
+    env.as_contract(&client.address, || {
+        let key = DataKey::Deposit(1);
+        assert!(env.storage().persistent().has(&key));
+        assert!(env.storage().persistent().get_ttl(&key) > 30 * 17280);
+    });
+}
+
+#[test]
+fn test_escrow_events() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    let expires_at = env.ledger().timestamp() + DEFAULT_ESCROW_PERIOD;
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::Deposited {
+            order_id: 1,
+            buyer: buyer.clone(),
+            token: usdc_token.clone(),
+            amount: 100_0000000,
+            expires_at,
+        }
+        .to_xdr(&env, &client.address)]
+    );
+    
+    client.release(&admin, &1u64);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::Released {
+            order_id: 1,
+            seller: seller.clone(),
+            token: usdc_token.clone(),
+            seller_share: 98_0000000,
+            fee: 2_0000000,
+        }
+        .to_xdr(&env, &client.address)]
+    );
+    
+    client.deposit(&buyer, &usdc_token, &50_0000000, &2u64);
+    client.refund(&admin, &2u64);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::Refunded {
+            order_id: 2,
+            buyer: buyer.clone(),
+            token: usdc_token.clone(),
+            amount: 50_0000000,
+        }
+        .to_xdr(&env, &client.address)]
+    );
+    
+    client.deposit(&buyer, &usdc_token, &10_0000000, &3u64);
+    client.settle(&admin, &MAX_SETTLE_BATCH);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [
+            events::Released {
+                order_id: 3,
+                seller: seller.clone(),
+                token: usdc_token.clone(),
+                seller_share: 9_8000000,
+                fee: 2000000,
+            }
+            .to_xdr(&env, &client.address),
+            events::Settled {
+                seller,
+                paid: Map::from_array(&env, [(usdc_token.clone(), 9_8000000)]),
+                fees: Map::from_array(&env, [(usdc_token.clone(), 2000000)]),
+                orders_released: 1,
+                orders_deferred: 0,
+            }
+            .to_xdr(&env, &client.address),
+        ]
+    );
+}
+
+#[test]
+fn test_config_events() {
+    let env = Env::default();
+    let (client, usdc_token, _, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let fee_terms = FeeTerms { buyer_surcharge_bps: 100, seller_commission_bps: 300 };
+    let change = ConfigChange::FeeTerms(fee_terms.clone());
+    let effective_at = client.queue_change(&admin, &change);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::ChangeQueued { kind: ChangeKind::FeeTerms, change, effective_at }
+            .to_xdr(&env, &client.address)]
+    );
+    
+    env.ledger().with_mut(|li| li.timestamp = effective_at);
+    client.apply_change(&admin, &ChangeKind::FeeTerms);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::FeeTermsUpdated { fee_terms: fee_terms.clone() }.to_xdr(&env, &client.address)]
+    );
+    assert_eq!(client.get_fee_terms(), fee_terms);
+    
+    let config = ReserveConfig { accepted: false, ..ReserveConfig::wallet_only(7) };
+    client.set_reserve(&admin, &usdc_token, &config);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::ReserveUpdated { token: usdc_token, config }.to_xdr(&env, &client.address)]
+    );
+}
+
+#[test]
+fn test_pause_blocks_deposits_but_not_refunds() {
+    let env = Env::default();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let usdc = TokenClient::new(&env, &usdc_token);
+    let pauser = Address::generate(&env);
+    
+    client.deposit(&buyer, &usdc_token, &100_0000000, &1u64);
+    
+    assert_eq!(client.try_pause(&pauser), Err(Ok(PoolError::Unauthorized)));
+    client.grant_role(&Role::Pauser, &pauser);
+    client.pause(&pauser);
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::Paused { caller: pauser.clone() }.to_xdr(&env, &client.address)]
+    );
+    assert!(client.is_paused());
+    
+    assert_eq!(
+        client.try_deposit(&buyer, &usdc_token, &50_0000000, &2u64),
+        Err(Ok(PoolError::ContractPaused))
+    );
+    assert_eq!(client.try_supply_to_blend(&admin), Err(Ok(PoolError::ContractPaused)));
+    
+    // Buyers can still be made whole
+    client.refund(&admin, &1u64);
+    assert_eq!(usdc.balance(&buyer), 1000_0000000);
+    
+    client.unpause(&pauser);
+    assert!(!client.is_paused());
+    client.deposit(&buyer, &usdc_token, &50_0000000, &2u64);
+}
+
+#[test]
+fn test_emergency_withdraw_from_blend() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &xlm_token]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    add_blend_reserve(&env, &client, &xlm_token, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &xlm_token, &40_0000000, &2u64);
+    client.supply_to_blend(&admin);
+    let usdc = TokenClient::new(&env, &blend_usdc);
+    let xlm = TokenClient::new(&env, &xlm_token);
+    assert_eq!(usdc.balance(&client.address), 0);
+    assert_eq!(xlm.balance(&client.address), 0);
+    
+    // Only allowed once deposits can no longer re-supply the funds
+    assert_eq!(
+        client.try_emergency_withdraw_from_blend(&admin),
+        Err(Ok(PoolError::NotPaused))
+    );
+    client.pause(&admin);
+    
+    let withdrawn = client.emergency_withdraw_from_blend(&admin);
+    assert_eq!(withdrawn.len(), 2);
+    let withdrawn_usdc = withdrawn.get(blend_usdc.clone()).unwrap();
+    let withdrawn_xlm = withdrawn.get(xlm_token.clone()).unwrap();
+    assert!(withdrawn_usdc >= 99_9999999);
+    assert!(withdrawn_xlm >= 39_9999999);
+    assert_eq!(usdc.balance(&client.address), withdrawn_usdc);
+    assert_eq!(xlm.balance(&client.address), withdrawn_xlm);
+    
+    let status = client.get_status(&blend_usdc);
+    assert_eq!(status.blend_position, 0);
+    assert!(!status.supplied_to_blend);
+    assert_eq!(client.get_status(&xlm_token).blend_position, 0);
+    assert_eq!(client.get_ledger(&blend_usdc).b_tokens, 0);
+    
+    // Refunds are paid straight from the wallet
+    client.refund(&admin, &1u64);
+    assert_eq!(usdc.balance(&buyer), 1000_0000000);
+}
+
+#[test]
+fn test_emergency_withdraw_skips_failing_reserve() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let eurc_sac = env.register_stellar_asset_contract_v2(Address::generate(&env));
+    eurc_sac.issuer().set_flag(IssuerFlags::RevocableFlag);
+    let eurc = eurc_sac.address();
+    StellarAssetClient::new(&env, &eurc).mint(&buyer, &1000_0000000);
+    let usdc_pool = create_blend_pool(&env, &[&blend_usdc]);
+    let eurc_pool = create_blend_pool(&env, &[&eurc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &usdc_pool);
+    add_blend_reserve(&env, &client, &eurc, &eurc_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &eurc, &40_0000000, &2u64);
+    client.supply_to_blend(&admin);
+    client.pause(&admin);
+    
+    // The EURC pool can no longer pay out, so its withdrawal fails
+    StellarAssetClient::new(&env, &eurc).set_authorized(&eurc_pool, &false);
+    let b_tokens = client.get_ledger(&eurc).b_tokens;
+    let withdrawn = client.emergency_withdraw_from_blend(&admin);
+    assert_eq!(withdrawn.len(), 1);
+    assert!(withdrawn.get(blend_usdc.clone()).unwrap() >= 99_9999999);
+    assert!(env.events().all().filter_by_contract(&client.address).events().contains(
+        &events::WithdrawFailed {
+            token: eurc.clone(),
+            pool: eurc_pool.clone(),
+            error: PoolError::BlendOperationFailed as u32,
+        }.to_xdr(&env, &client.address)
+    ));
+    assert_eq!(client.get_ledger(&eurc).b_tokens, b_tokens);
+    assert_eq!(TokenClient::new(&env, &eurc).balance(&client.address), 0);
+    
+    // Once the pool recovers, the next call brings the rest out
+    StellarAssetClient::new(&env, &eurc).set_authorized(&eurc_pool, &true);
+    let withdrawn = client.emergency_withdraw_from_blend(&admin);
+    assert_eq!(withdrawn.keys(), soroban_sdk::vec![&env, eurc.clone()]);
+    assert_eq!(client.get_ledger(&eurc).b_tokens, 0);
+}
+
+#[test]
+fn test_new_asset_is_supplied_through_reserve_config() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    // A third asset needs only a reserve config, not a new storage key
+    let eurc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&eurc]);
+    let reserve_index = pool::Client::new(&env, &blend_pool).get_reserve(&eurc).config.index;
+    client.set_reserve(&admin, &eurc, &ReserveConfig {
+        accepted: true,
+        blend_pool: Some(blend_pool.clone()),
+        reserve_index,
+        supply_threshold: 50_0000000,
+        buffer_bps: 0,
+        supply_mode: SupplyMode::Supply,
+        max_util_bps: 9000,
+        decimals: 7,
+        enabled: true,
+    });
+    let eurc_client = TokenClient::new(&env, &eurc);
+    
+    // Balances below the supply threshold stay in the wallet
+    client.deposit(&buyer, &eurc, &30_0000000, &1u64);
+    assert_eq!(client.supply_to_blend(&admin), Map::new(&env));
+    assert_eq!(eurc_client.balance(&client.address), 30_0000000);
+    assert_eq!(client.get_status(&eurc).blend_position, 0);
+    
+    client.deposit(&buyer, &eurc, &30_0000000, &2u64);
+    client.supply_to_blend(&admin);
+    assert_eq!(eurc_client.balance(&client.address), 0);
+    assert_eq!(client.get_ledger(&eurc).supplied, 60_0000000);
+    
+    let paid = client.settle(&admin, &MAX_SETTLE_BATCH);
+    assert_eq!(paid, Map::from_array(&env, [(eurc.clone(), 58_8000000)]));
+    assert_eq!(eurc_client.balance(&seller), 58_8000000);
+}
+
+#[test]
+fn test_liquidity_buffer_pays_refunds_from_wallet() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    let config = ReserveConfig { buffer_bps: 1000, ..client.get_reserve(&blend_usdc).unwrap() };
+    client.set_reserve(&admin, &blend_usdc, &config);
+    let usdc = TokenClient::new(&env, &blend_usdc);
+    
+    // 10% of what the pool owes stays liquid
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    assert_eq!(usdc.balance(&client.address), 10_0000000);
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, 90_0000000);
+    client.deposit(&buyer, &blend_usdc, &5_0000000, &2u64);
+    client.supply_to_blend(&admin);
+    assert_eq!(usdc.balance(&client.address), 10_5000000);
+    
+    // A small refund is paid from the buffer without touching Blend
+    client.refund(&admin, &2u64);
+    assert_eq!(usdc.balance(&client.address), 5_5000000);
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, 94_5000000);
+    
+    // Rebalancing refills the buffer from Blend...
+    assert_eq!(client.rebalance(&admin, &blend_usdc), -4_5000000);
+    assert_eq!(usdc.balance(&client.address), 10_0000000);
+    assert_eq!(client.rebalance(&admin, &blend_usdc), 0);
+    
+    // ...and sweeps anything above it into Blend
+    client.set_reserve(&admin, &blend_usdc, &ReserveConfig { buffer_bps: 500, ..config.clone() });
+    assert_eq!(client.rebalance(&admin, &blend_usdc), 5_0000000);
+    assert_eq!(usdc.balance(&client.address), 5_0000000);
+    
+    let invalid = ReserveConfig { buffer_bps: 10001, ..config };
+    assert_eq!(client.try_set_reserve(&admin, &blend_usdc, &invalid), Err(Ok(PoolError::InvalidAmount)));
+    assert_eq!(
+        client.try_rebalance(&admin, &client.get_accepted_tokens().get(0).unwrap()),
+        Err(Ok(PoolError::BlendPoolNotConfigured))
+    );
+}
+
+#[test]
+fn test_deposits_are_supplied_in_one_batch() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &xlm_token]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    add_blend_reserve(&env, &client, &xlm_token, &blend_pool);
+    
+    // Deposits only record funds
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &xlm_token, &40_0000000, &2u64);
+    client.deposit(&buyer, &blend_usdc, &20_0000000, &3u64);
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, 0);
+    assert_eq!(TokenClient::new(&env, &blend_usdc).balance(&client.address), 120_0000000);
+    
+    // Both reserves go to their shared pool in one submit
+    let supplied = client.supply_to_blend(&admin);
+    assert_eq!(
+        supplied,
+        Map::from_array(&env, [(blend_usdc.clone(), 120_0000000), (xlm_token.clone(), 40_0000000)])
+    );
+    let supplied_event = |i: u32| {
+        let token = supplied.keys().get_unchecked(i);
+        let amount = supplied.get_unchecked(token.clone());
+        events::SuppliedToBlend { token, pool: blend_pool.clone(), amount }.to_xdr(&env, &client.address)
+    };
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [supplied_event(0), supplied_event(1)]
+    );
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, 120_0000000);
+    assert_eq!(client.get_ledger(&xlm_token).supplied, 40_0000000);
+    
+    // Nothing left above the threshold
+    assert_eq!(client.supply_to_blend(&admin), Map::new(&env));
+}
+
+/// CPU instructions metered by the last top-level invocation
+fn last_cpu_cost(env: &Env) -> u64 {
+    env.cost_estimate().budget().cpu_instruction_cost()
+}
+
+#[test]
+fn test_batched_supply_budget() {
+    const ORDERS: u64 = 5;
+    
+    // One Blend submit per deposit, as deposits used to do
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    let mut per_deposit_cost = 0;
+    for order_id in 1..=ORDERS {
+        client.deposit(&buyer, &blend_usdc, &10_0000000, &order_id);
+        per_deposit_cost += last_cpu_cost(&env);
+        client.supply_to_blend(&admin);
+        per_deposit_cost += last_cpu_cost(&env);
+    }
+    
+    // Deposits only record funds and the keeper supplies them once
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    let mut deposit_cost = 0;
+    for order_id in 1..=ORDERS {
+        client.deposit(&buyer, &blend_usdc, &10_0000000, &order_id);
+        deposit_cost += last_cpu_cost(&env);
+    }
+    client.supply_to_blend(&admin);
+    let batched_cost = deposit_cost + last_cpu_cost(&env);
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, 50_0000000);
+    
+    // A buyer's deposit no longer pays for a Blend submit
+    assert!(deposit_cost * 2 < per_deposit_cost, "deposits {} vs {}", deposit_cost, per_deposit_cost);
+    assert!(batched_cost * 2 < per_deposit_cost, "batched {} vs {}", batched_cost, per_deposit_cost);
+}
+
+#[test]
+fn test_frozen_blend_pool_leaves_supply_pending() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    blend_client.set_status(&4); // admin frozen
+    
+    // Checkout never depends on Blend
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    
+    // The keeper skips the frozen pool and the funds stay safe in the wallet
+    assert_eq!(client.supply_to_blend(&admin), Map::new(&env));
+    assert_eq!(
+        env.events().all().filter_by_contract(&client.address),
+        [events::SupplyFailed {
+            token: blend_usdc.clone(),
+            pool: blend_pool.clone(),
+            amount: 100_0000000,
+            error: PoolError::BlendPoolUnhealthy as u32,
+        }
+        .to_xdr(&env, &client.address)]
+    );
+    assert_eq!(TokenClient::new(&env, &blend_usdc).balance(&client.address), 100_0000000);
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, 0);
+    
+    client.supply_to_blend(&admin);
+    let pending = client.get_pending_supply(&blend_usdc).unwrap();
+    assert_eq!(pending.amount, 100_0000000);
+    assert_eq!(pending.error, PoolError::BlendPoolUnhealthy as u32);
+    assert_eq!(pending.attempts, 2);
+    
+    // Refunds are paid from the idle funds
+    client.deposit(&buyer, &blend_usdc, &20_0000000, &2u64);
+    client.refund(&admin, &2u64);
+    
+    // Once the pool is active again the next run supplies and clears the record
+    blend_client.set_status(&3);
+    blend_client.update_status();
+    assert_eq!(
+        client.supply_to_blend(&admin),
+        Map::from_array(&env, [(blend_usdc.clone(), 100_0000000)])
+    );
+    assert_eq!(client.get_pending_supply(&blend_usdc), None);
+}
+
+#[test]
+fn test_capped_reserve_does_not_block_batch() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, xlm_token, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let capped = pool::ReserveConfig { index: 1, supply_cap: 50_0000000, ..default_reserve_config() };
+    let blend_pool = deploy_blend_pool(&env, [(&blend_usdc, default_reserve_config()), (&xlm_token, capped)]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    add_blend_reserve(&env, &client, &xlm_token, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.deposit(&buyer, &xlm_token, &80_0000000, &2u64);
+    
+    // XLM is over the reserve's supply cap; USDC still goes in on its own
+    assert_eq!(
+        client.supply_to_blend(&admin),
+        Map::from_array(&env, [(blend_usdc.clone(), 100_0000000)])
+    );
+    assert_eq!(client.get_pending_supply(&blend_usdc), None);
+    let pending = client.get_pending_supply(&xlm_token).unwrap();
+    assert_eq!(pending.amount, 80_0000000);
+    assert_eq!(pending.error, PoolError::BlendOperationFailed as u32);
+    assert_eq!(TokenClient::new(&env, &xlm_token).balance(&client.address), 80_0000000);
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, 100_0000000);
+}
+
+#[test]
+fn test_reserves_use_plain_supply_by_default() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, usdc_token, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    assert_eq!(client.get_reserve(&usdc_token).unwrap().supply_mode, SupplyMode::Supply);
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    
+    // The float is never counted as collateral
+    let positions = pool::Client::new(&env, &blend_pool).get_positions(&client.address);
+    assert_eq!(positions.supply.get(0), Some(client.get_ledger(&blend_usdc).b_tokens));
+    assert!(positions.collateral.is_empty());
+    
+    client.refund(&admin, &1u64);
+    assert_eq!(TokenClient::new(&env, &blend_usdc).balance(&buyer), 1000_0000000);
+}
+
+#[test]
+fn test_migrate_collateral_position_to_supply() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, seller) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    let config = client.get_reserve(&blend_usdc).unwrap();
+    client.set_reserve(&admin, &blend_usdc, &ReserveConfig { supply_mode: SupplyMode::Collateral, ..config.clone() });
+    
+    // A position opened as SupplyCollateral, as every reserve used to be
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    client.supply_to_blend(&admin);
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    assert!(blend_client.get_positions(&client.address).collateral.get(0).unwrap() > 0);
+    
+    // Switching modes with an open position goes through the migration
+    assert_eq!(
+        client.try_set_reserve(&admin, &blend_usdc, &config),
+        Err(Ok(PoolError::MigrationRequired))
+    );
+    
+    // Not while paused, nor while the pool couldn't take the funds back
+    client.pause(&admin);
+    assert_eq!(
+        client.try_migrate_supply_mode(&admin, &blend_usdc, &SupplyMode::Supply),
+        Err(Ok(PoolError::ContractPaused))
+    );
+    client.unpause(&admin);
+    blend_client.set_status(&4); // admin frozen
+    assert_eq!(
+        client.try_migrate_supply_mode(&admin, &blend_usdc, &SupplyMode::Supply),
+        Err(Ok(PoolError::BlendPoolUnhealthy))
+    );
+    blend_client.set_status(&0); // admin active
+    
+    let moved = client.migrate_supply_mode(&admin, &blend_usdc, &SupplyMode::Supply);
+    assert!(moved >= 99_9999999);
+    
+    let positions = blend_client.get_positions(&client.address);
+    assert!(positions.collateral.is_empty());
+    assert_eq!(positions.supply.get(0), Some(client.get_ledger(&blend_usdc).b_tokens));
+    assert_eq!(client.get_reserve(&blend_usdc).unwrap().supply_mode, SupplyMode::Supply);
+    assert_eq!(client.get_ledger(&blend_usdc).supplied, moved);
+    assert_eq!(TokenClient::new(&env, &blend_usdc).balance(&client.address), 0);
+    
+    // Nothing left to move, and the migrated position still pays out
+    assert_eq!(client.migrate_supply_mode(&admin, &blend_usdc, &SupplyMode::Supply), 0);
+    assert_eq!(client.settle(&admin, &MAX_SETTLE_BATCH).get(blend_usdc.clone()), Some(98_0000000));
+    assert_eq!(TokenClient::new(&env, &blend_usdc).balance(&seller), 98_0000000);
+}
+
+#[test]
+fn test_set_reserve_requires_a_blend_reserve() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, usdc_token, xlm_token, _, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let blend_pool = create_blend_pool(&env, &[&usdc_token]);
+    let config = ReserveConfig { blend_pool: Some(blend_pool.clone()), ..ReserveConfig::wallet_only(7) };
+    
+    // XLM is not listed by the pool, and USDC is listed at index 0
+    assert_eq!(client.try_set_reserve(&admin, &xlm_token, &config), Err(Ok(PoolError::NotBlendReserve)));
+    let wrong_index = ReserveConfig { reserve_index: 1, ..config.clone() };
+    assert_eq!(client.try_set_reserve(&admin, &usdc_token, &wrong_index), Err(Ok(PoolError::NotBlendReserve)));
+    let not_a_pool = ReserveConfig { blend_pool: Some(Address::generate(&env)), ..config.clone() };
+    assert_eq!(client.try_set_reserve(&admin, &usdc_token, &not_a_pool), Err(Ok(PoolError::NotBlendReserve)));
+    client.set_reserve(&admin, &usdc_token, &config);
+    
+    let invalid = ReserveConfig { max_util_bps: 10001, ..config };
+    assert_eq!(client.try_set_reserve(&admin, &usdc_token, &invalid), Err(Ok(PoolError::InvalidAmount)));
+}
+
+#[test]
+fn test_supply_skips_unhealthy_blend_reserves() {
+    let env = Env::default();
+    env.cost_estimate().budget().reset_unlimited();
+    let (client, _, _, buyer, _) = setup_escrow(&env);
+    let admin = client.get_admin();
+    
+    let borrower = Address::generate(&env);
+    let blend_usdc = create_token(&env, &buyer, 1000_0000000);
+    let collateral = create_token(&env, &borrower, 1000_0000000);
+    let blend_pool = create_blend_pool(&env, &[&blend_usdc, &collateral]);
+    add_blend_reserve(&env, &client, &blend_usdc, &blend_pool);
+    let config = ReserveConfig { max_util_bps: 5000, ..client.get_reserve(&blend_usdc).unwrap() };
+    client.set_reserve(&admin, &blend_usdc, &config);
+    let blend_client = pool::Client::new(&env, &blend_pool);
+    let usdc = TokenClient::new(&env, &blend_usdc);
+    
+    // A borrower takes 60% of the reserve's supply
+    usdc.transfer(&buyer, &borrower, &100_0000000);
+    blend_client.submit(&borrower, &borrower, &borrower, &soroban_sdk::vec![
+        &env,
+        pool::Request { request_type: 0, address: blend_usdc.clone(), amount: 100_0000000 },
+        pool::Request { request_type: 2, address: collateral.clone(), amount: 1000_0000000 },
+        pool::Request { request_type: 4, address: blend_usdc.clone(), amount: 60_0000000 },
+    ]);
+    
+    // Above the 50% ceiling nothing is supplied
+    client.deposit(&buyer, &blend_usdc, &100_0000000, &1u64);
+    assert_eq!(client.supply_to_blend(&admin), Map::new(&env));
+    assert_eq!(
+        client.get_pending_supply(&blend_usdc).unwrap().error,
+        PoolError::UtilizationTooHigh as u32
+    );
+    assert_eq!(client.rebalance(&admin, &blend_usdc), 0);
+    assert_eq!(usdc.balance(&client.address), 100_0000000);
+    
+    // On ice pools are skipped too
+    client.set_reserve(&admin, &blend_usdc, &ReserveConfig { max_util_bps: 9000, ..config });
+    blend_client.set_status(&2); // admin on ice
+    assert_eq!(client.supply_to_blend(&admin), Map::new(&env));
+    assert_eq!(
+        client.get_pending_supply(&blend_usdc).unwrap().error,
+        PoolError::BlendPoolUnhealthy as u32
+    );
+    
+    blend_client.set_status(&0); // admin active
+    assert_eq!(
+        client.supply_to_blend(&admin),
+        Map::from_array(&env, [(blend_usdc.clone(), 100_0000000)])
+    );
+    assert_eq!(client.get_pending_supply(&blend_usdc), None);
+}
+
@@ -14,8 +14,11 @@ pub struct ZmokeMinter;
 
 #[contractimpl]
 impl ZmokeMinter {
-    /// Initialize with token address and admin
-    pub fn initialize(env: Env, token: Address, admin: Address) {
+    /// Configure the token and admin at deploy time
+    /// 
+    /// Runs once, atomically with deployment, so the admin can't be front-run
+    /// or replaced later.
+    pub fn __constructor(env: Env, token: Address, admin: Address) {
         env.storage().instance().set(&Symbol::new(&env, "token"), &token);
         env.storage().instance().set(&Symbol::new(&env, "admin"), &admin);
     }
@@ -42,5 +45,4 @@ impl ZmokeMinter {
     }
 }
 
-#[cfg(test)]
 mod test;
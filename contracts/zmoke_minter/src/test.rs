@@ -1,7 +1,11 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{testutils::Address as _, Env};
+use soroban_sdk::{
+    testutils::{Address as _, MockAuth, MockAuthInvoke},
+    xdr::{ScErrorCode, ScErrorType},
+    Env, Error, IntoVal,
+};
 
 #[test]
 fn test_mint_and_burn() {
@@ -11,13 +15,11 @@ fn test_mint_and_burn() {
     // 1. Setup
     let admin = Address::generate(&env);
     let user = Address::generate(&env);
-    // 2. Initialize
+    // 2. Deploy with token and admin
     let token_contract = env.register_stellar_asset_contract_v2(admin.clone()).address();
-    let minter_id = env.register(ZmokeMinter, ());
+    let minter_id = env.register(ZmokeMinter, (&token_contract, &admin));
     let client = ZmokeMinterClient::new(&env, &minter_id);
 
-    client.initialize(&token_contract, &admin);
-
     // 3. Mint Rewards
     client.mint_rewards(&user, &1000);
     
@@ -27,3 +29,39 @@ fn test_mint_and_burn() {
     // 4. Burn
     client.burn(&user, &500);
 }
+
+#[test]
+fn test_admin_cannot_be_replaced_after_deploy() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let user = Address::generate(&env);
+    let token_contract = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let minter_id = env.register(ZmokeMinter, (&token_contract, &admin));
+    let client = ZmokeMinterClient::new(&env, &minter_id);
+
+    // The constructor can't be invoked again once deployed
+    let result = env.try_invoke_contract::<(), Error>(
+        &minter_id,
+        &Symbol::new(&env, "__constructor"),
+        (&token_contract, &attacker).into_val(&env),
+    );
+    assert_eq!(
+        result,
+        Err(Ok(Error::from_type_and_code(ScErrorType::Context, ScErrorCode::InvalidAction)))
+    );
+
+    // Minting still requires the original admin
+    env.mock_auths(&[MockAuth {
+        address: &attacker,
+        invoke: &MockAuthInvoke {
+            contract: &minter_id,
+            fn_name: "mint_rewards",
+            args: (&user, 1000i128).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_mint_rewards(&user, &1000).is_err());
+}
@@ -184,9 +184,11 @@ export const soroban = {
   async releaseEscrow(escrowId: number, adminAddress: string) {
     const account = await server.getAccount(adminAddress);
     const contract = new Contract(POOL_CONTRACT_ID);
+    // release(caller: Address, order_id: u64) - caller must hold the Settler role
     const op = contract.call(
       "release",
-      nativeToScVal(escrowId, { type: "u64" }),
+      nativeToScVal(adminAddress, { type: "address" }), // caller
+      nativeToScVal(escrowId, { type: "u64" }), // order_id
     );
 
     let tx = new TransactionBuilder(account, { fee: "1000" })